@@ -0,0 +1,302 @@
+use crate::components::class_code_route;
+use crate::models::{ClassCodeTarget, ClassData};
+use crate::routes::{CodeDisplayOptions, SiteId};
+use crate::utils::*;
+use crate::Route;
+use dioxus::prelude::*;
+
+// 课程详情：显示状态和课次记录，可修改课程名称和班级，管理分享链接。
+// 按 site_id 访问，关闭按 id 访问后仍可使用
+#[component]
+pub fn ClassDetail(site_id: SiteId) -> Element {
+    let mut class_data = use_signal(|| None::<ClassData>);
+    let mut loading = use_signal(|| true);
+    let mut load_error = use_signal(String::new);
+
+    // 编辑中的内容，加载完成后填入当前值
+    let mut class_name_input = use_signal(String::new);
+    let mut classes_input = use_signal(String::new);
+    let mut saving = use_signal(|| false);
+    let mut save_message = use_signal(String::new);
+    let mut save_error = use_signal(String::new);
+    let mut share_error = use_signal(String::new);
+
+    let load_site_id = site_id.clone();
+    use_effect(move || {
+        let site_id = load_site_id.clone();
+        spawn(async move {
+            loading.set(true);
+            match get_class_data(site_id.to_string()).await {
+                Ok(Some(data)) => {
+                    class_name_input.set(data.class_name.clone());
+                    classes_input.set(data.classes.clone());
+                    class_data.set(Some(data));
+                }
+                Ok(None) => load_error.set("未找到指定的课程数据".to_string()),
+                Err(e) => load_error.set(format!("加载数据失败: {}", api_error_message(&e))),
+            }
+            loading.set(false);
+        });
+    });
+
+    let lessons_site_id = site_id.clone();
+    let lessons = use_resource(move || list_course_lessons(lessons_site_id.to_string()));
+
+    // 分享 slug 只对持有令牌的用户返回，未登录时提示登录
+    let share_site_id = site_id.clone();
+    let mut share_slug = use_resource(move || get_share_slug(share_site_id.to_string()));
+    let known_share_slug = match &*share_slug.read() {
+        Some(Ok(slug)) => slug.clone(),
+        _ => None,
+    };
+
+    // 签到码页面优先使用分享链接，否则使用服务器给出的地址。
+    // 关闭按 id 访问且未登录时没有可用的地址，不显示链接
+    let target_site_id = site_id.clone();
+    let mut code_target =
+        use_resource(move || get_class_code_target(target_site_id.to_string()));
+    let code_route = match (known_share_slug, &*code_target.read()) {
+        (Some(slug), _) => Some(ClassCodeTarget::Slug(slug)),
+        (None, Some(Ok(target))) => target.clone(),
+        _ => None,
+    }
+    .and_then(|target| class_code_route(&target, CodeDisplayOptions::default()));
+
+    // 生成新的分享链接（原链接失效），或停用分享链接
+    let mut update_share = move |rotate: bool| {
+        let Some(current) = class_data() else {
+            return;
+        };
+        share_error.set(String::new());
+        spawn(async move {
+            let result = if rotate {
+                rotate_share_slug(current.site_id.clone()).await.map(|_| ())
+            } else {
+                revoke_share_slug(current.site_id.clone()).await
+            };
+            match result {
+                Ok(()) => {
+                    share_slug.restart();
+                    code_target.restart();
+                }
+                Err(e) => share_error.set(format!("操作失败: {}", api_error_message(&e))),
+            }
+        });
+    };
+
+    // 先在页面上显示修改后的内容，保存失败时恢复原来的内容
+    let save = move |_| {
+        let Some(previous) = class_data() else {
+            return;
+        };
+        save_message.set(String::new());
+        save_error.set(String::new());
+        let fields = validate_class_field("课程名称", &class_name_input()).and_then(|class_name| {
+            validate_class_field("班级", &classes_input()).map(|classes| (class_name, classes))
+        });
+        let (class_name, classes) = match fields {
+            Ok(fields) => fields,
+            Err(e) => {
+                save_error.set(e.message());
+                return;
+            }
+        };
+        if class_name == previous.class_name && classes == previous.classes {
+            save_message.set("内容没有变化".to_string());
+            return;
+        }
+
+        class_data.set(Some(ClassData {
+            class_name: class_name.clone(),
+            classes: classes.clone(),
+            ..previous.clone()
+        }));
+        saving.set(true);
+        spawn(async move {
+            match update_class_info(previous.site_id.clone(), Some(class_name), Some(classes)).await
+            {
+                Ok(data) => {
+                    class_name_input.set(data.class_name.clone());
+                    classes_input.set(data.classes.clone());
+                    save_message.set(format!("已保存 - {}", data.updated_at.to_local_string()));
+                    class_data.set(Some(data));
+                }
+                Err(e) => {
+                    class_data.set(Some(previous));
+                    save_error.set(format!("保存失败: {}", api_error_message(&e)));
+                }
+            }
+            saving.set(false);
+        });
+    };
+
+    rsx! {
+        div { id: "class-detail", class: "home-container",
+            div { class: "home-scanner-container",
+                h1 { class: "home-title", "课程详情" }
+
+                if loading() {
+                    div { class: "home-warning-message",
+                        p { "正在加载数据..." }
+                    }
+                } else if let Some(data) = class_data() {
+                    div { class: "course-info",
+                        p { "课程: {data.class_name}" }
+                        p { "班级: {data.classes}" }
+                        if let Some(last_time) = data.last_created_time.as_ref() {
+                            p { "上次创建: {last_time.to_local_string()}" }
+                        }
+                        p {
+                            if data.is_expired {
+                                "状态: 已过期"
+                            } else {
+                                "状态: 有效"
+                            }
+                        }
+                        p { "更新于: {data.updated_at.to_local_string()}" }
+                    }
+
+                    div { class: "class-detail-form",
+                        label {
+                            "课程名称"
+                            input {
+                                r#type: "text",
+                                class: "home-paste-input",
+                                maxlength: "{MAX_CLASS_FIELD_CHARS}",
+                                value: "{class_name_input}",
+                                oninput: move |event| class_name_input.set(event.value()),
+                            }
+                        }
+                        label {
+                            "班级"
+                            input {
+                                r#type: "text",
+                                class: "home-paste-input",
+                                maxlength: "{MAX_CLASS_FIELD_CHARS}",
+                                value: "{classes_input}",
+                                oninput: move |event| classes_input.set(event.value()),
+                            }
+                        }
+                    }
+
+                    if !save_error().is_empty() {
+                        div { class: "home-error-message",
+                            p { "{save_error()}" }
+                        }
+                    } else if !save_message().is_empty() {
+                        div { class: "home-success-message",
+                            p { "{save_message()}" }
+                        }
+                    }
+
+                    div { class: "button-group",
+                        button {
+                            class: "home-button-primary",
+                            disabled: saving(),
+                            onclick: save,
+                            if saving() {
+                                "保存中..."
+                            } else {
+                                "保存"
+                            }
+                        }
+                        if let Some(route) = code_route.clone() {
+                            Link { to: route, class: "class-detail-link", "显示签到码" }
+                        }
+                    }
+
+                    h3 { class: "class-detail-heading", "分享链接" }
+                    match &*share_slug.read_unchecked() {
+                        None => rsx! {
+                            div { class: "course-info",
+                                p { "正在加载分享链接..." }
+                            }
+                        },
+                        Some(Err(ServerFnError::WrappedServerError(ApiError::Unauthorized))) => rsx! {
+                            div { class: "course-info",
+                                p { "登录后可以查看和管理分享链接。" }
+                            }
+                            div { class: "button-group",
+                                Link { to: Route::SignIn {}, class: "class-detail-link", "登录" }
+                            }
+                        },
+                        Some(Err(e)) => rsx! {
+                            div { class: "home-error-message",
+                                p { "加载分享链接失败: {api_error_message(e)}" }
+                            }
+                        },
+                        Some(Ok(slug)) => rsx! {
+                            div { class: "course-info",
+                                if let Some(slug) = slug.as_ref() {
+                                    p { class: "class-detail-share", "/s/{slug}" }
+                                    p { "更换后原链接立即失效。" }
+                                } else {
+                                    p { "分享链接已停用，可以重新生成。" }
+                                }
+                            }
+                            if !share_error().is_empty() {
+                                div { class: "home-error-message",
+                                    p { "{share_error()}" }
+                                }
+                            }
+                            div { class: "button-group",
+                                button {
+                                    class: "home-button-primary",
+                                    onclick: move |_| update_share(true),
+                                    if slug.is_some() {
+                                        "更换链接"
+                                    } else {
+                                        "生成链接"
+                                    }
+                                }
+                                if slug.is_some() {
+                                    button {
+                                        class: "home-button-danger",
+                                        onclick: move |_| update_share(false),
+                                        "停用链接"
+                                    }
+                                }
+                            }
+                        },
+                    }
+
+                    h3 { class: "class-detail-heading", "课次记录" }
+                    match &*lessons.read_unchecked() {
+                        Some(Ok(lessons)) if lessons.is_empty() => rsx! {
+                            p { class: "class-detail-empty", "还没有课次记录" }
+                        },
+                        Some(Ok(lessons)) => rsx! {
+                            ul { class: "class-list",
+                                for lesson in lessons.iter() {
+                                    li { key: "{lesson.id}", class: "class-list-item class-detail-lesson",
+                                        div { class: "class-list-name", "课次 {lesson.class_lesson_id}" }
+                                        p { "首次出现: {lesson.first_seen.to_local_string()}" }
+                                        p { "最近出现: {lesson.last_seen.to_local_string()}" }
+                                        p { "签到次数: {lesson.checkwork_ids.len()}" }
+                                    }
+                                }
+                            }
+                        },
+                        Some(Err(ServerFnError::WrappedServerError(ApiError::Unauthorized))) => rsx! {
+                            p { class: "class-detail-empty", "登录后可以查看课次记录" }
+                        },
+                        Some(Err(e)) => rsx! {
+                            div { class: "home-error-message",
+                                p { "加载课次失败: {api_error_message(e)}" }
+                            }
+                        },
+                        None => rsx! {
+                            p { class: "class-detail-empty", "正在加载课次..." }
+                        },
+                    }
+                } else {
+                    div { class: "home-error-message",
+                        h3 { "错误:" }
+                        p { "{load_error()}" }
+                        Link { to: Route::ClassList {}, class: "class-detail-link", "返回课程列表" }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::models::{ClassData, ClassQuery};
+use crate::routes::{ClassId, CodeDisplayOptions, SiteId};
+use crate::utils::*;
+use crate::Route;
+use dioxus::prelude::*;
+
+// 每页显示的课程数
+const CLASS_LIST_PAGE_SIZE: u32 = 20;
+
+// 所有已知课程的列表，可按名称或班级搜索、按状态筛选，点击进入课程详情页
+#[component]
+pub fn ClassList() -> Element {
+    let mut search_input = use_signal(String::new);
+    let mut search = use_signal(String::new);
+    let mut is_expired = use_signal(|| None::<bool>);
+    let mut offset = use_signal(|| 0u32);
+
+    let page = use_resource(move || async move {
+        let search = search();
+        list_all_classes(ClassQuery {
+            is_expired: is_expired(),
+            search: (!search.is_empty()).then_some(search),
+            offset: Some(offset()),
+            limit: Some(CLASS_LIST_PAGE_SIZE),
+            ..Default::default()
+        })
+        .await
+    });
+
+    // 修改搜索或筛选条件后回到第一页
+    let mut apply_search = move || {
+        search.set(search_input().trim().to_string());
+        offset.set(0);
+    };
+
+    rsx! {
+        div { id: "class-list", class: "home-container",
+            div { class: "home-scanner-container",
+                h1 { class: "home-title", "课程列表" }
+
+                div { class: "home-paste-group",
+                    input {
+                        r#type: "search",
+                        class: "home-paste-input",
+                        placeholder: "搜索课程名称或班级",
+                        value: "{search_input}",
+                        oninput: move |event| search_input.set(event.value()),
+                        onkeydown: move |event| {
+                            if event.key() == Key::Enter {
+                                apply_search();
+                            }
+                        },
+                    }
+                    button {
+                        class: "home-button-primary",
+                        onclick: move |_| apply_search(),
+                        "搜索"
+                    }
+                }
+
+                select {
+                    class: "class-list-filter",
+                    onchange: move |event| {
+                        is_expired
+                            .set(
+                                match event.value().as_str() {
+                                    "active" => Some(false),
+                                    "expired" => Some(true),
+                                    _ => None,
+                                },
+                            );
+                        offset.set(0);
+                    },
+                    option { value: "all", "全部课程" }
+                    option { value: "active", "有效" }
+                    option { value: "expired", "已过期" }
+                }
+
+                match &*page.read_unchecked() {
+                    None => rsx! {
+                        div { class: "home-warning-message",
+                            p { "正在加载课程..." }
+                        }
+                    },
+                    Some(Err(e)) => rsx! {
+                        div { class: "home-error-message",
+                            h3 { "错误:" }
+                            p { "加载课程失败: {api_error_message(e)}" }
+                        }
+                    },
+                    Some(Ok(page)) if page.classes.is_empty() => rsx! {
+                        div { class: "home-info-message",
+                            p {
+                                if search().is_empty() && is_expired().is_none() {
+                                    "还没有课程，扫描签到码后会出现在这里"
+                                } else {
+                                    "没有符合条件的课程"
+                                }
+                            }
+                        }
+                    },
+                    Some(Ok(page)) => {
+                        let page_count = page.total.div_ceil(page.limit as u64).max(1);
+                        let current_page = page.offset / page.limit + 1;
+                        let has_previous = page.offset > 0;
+                        let has_next = ((page.offset + page.limit) as u64) < page.total;
+                        let limit = page.limit;
+                        rsx! {
+                            ul { class: "class-list",
+                                for class in page.classes.iter() {
+                                    ClassListItem { key: "{class.id}", class: class.clone() }
+                                }
+                            }
+                            div { class: "class-list-pager",
+                                button {
+                                    class: "home-button-primary",
+                                    disabled: !has_previous,
+                                    onclick: move |_| offset.set(offset().saturating_sub(limit)),
+                                    "上一页"
+                                }
+                                span { "第 {current_page} / {page_count} 页，共 {page.total} 个课程" }
+                                button {
+                                    class: "home-button-primary",
+                                    disabled: !has_next,
+                                    onclick: move |_| offset.set(offset() + limit),
+                                    "下一页"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ClassListItem(class: ClassData) -> Element {
+    let code_age = match class.last_created_time.as_ref() {
+        Some(created_time) => format!(
+            "签到码生成于 {} 分钟前",
+            time_diff_from_now(&client_clock(), created_time)
+        ),
+        None => "还没有签到码".to_string(),
+    };
+    let route = match class.site_id.parse::<SiteId>() {
+        Ok(site_id) => Route::ClassDetail { site_id },
+        // site_id 不是数字的课程没有详情页，只能按 id 显示签到码
+        Err(_) => Route::ClassCode {
+            id: ClassId(class.id),
+            display: CodeDisplayOptions::default(),
+        },
+    };
+
+    rsx! {
+        li {
+            class: if class.is_expired { "class-list-item class-list-item-expired" } else { "class-list-item" },
+            Link { to: route,
+                div { class: "class-list-name",
+                    span { "{class.class_name}" }
+                    if class.is_expired {
+                        span { class: "class-list-badge", "已过期" }
+                    }
+                }
+                p { "班级: {class.classes}" }
+                p { "{code_age}" }
+            }
+        }
+    }
+}
@@ -1,10 +1,14 @@
-use crate::components::PageNotFound;
-use crate::models::ClassData;
+use crate::models::{ClassCodeTarget, ClassData};
+use crate::routes::{ClassId, CodeDisplayOptions, ShareSlug, SiteId};
 use crate::utils::*;
+use crate::Route;
 use dioxus::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::spawn_local;
 
+// 每隔多少秒重新加载课程数据
+const CLASS_DATA_RELOAD_SECS: u32 = 60;
+
 // 复制到剪贴板的函数
 fn copy_to_clipboard(text: &str) {
     let text = text.to_string();
@@ -23,7 +27,7 @@ fn copy_to_clipboard(text: &str) {
                         if let Ok(write_text_fn) = write_text.dyn_into::<js_sys::Function>() {
                             let args = js_sys::Array::new();
                             args.push(&text.into());
-                            if let Ok(_) = write_text_fn.apply(&clipboard_obj, &args) {
+                            if write_text_fn.apply(&clipboard_obj, &args).is_ok() {
                                 web_sys::console::log_1(&"复制成功".into());
                                 return;
                             }
@@ -37,50 +41,136 @@ fn copy_to_clipboard(text: &str) {
     });
 }
 
+// 签到码页面按哪种标识加载课程
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassLocator {
+    Id(ClassId),
+    Slug(ShareSlug),
+}
+
+impl ClassLocator {
+    async fn load(&self) -> Result<Option<ClassData>, ServerFnError<ApiError>> {
+        match self {
+            ClassLocator::Id(id) => get_class_data_by_id(id.0).await,
+            ClassLocator::Slug(slug) => get_class_data_by_slug(slug.to_string()).await,
+        }
+    }
+
+    // 课程更新事件流的地址，与加载数据使用同一标识
+    fn events_path(&self) -> String {
+        match self {
+            ClassLocator::Id(id) => format!("{}/i/{}", CLASS_EVENTS_PATH, id),
+            ClassLocator::Slug(slug) => format!("{}/s/{}", CLASS_EVENTS_PATH, slug),
+        }
+    }
+
+    fn route(&self, display: CodeDisplayOptions) -> Route {
+        match self {
+            ClassLocator::Id(id) => Route::ClassCode { id: *id, display },
+            ClassLocator::Slug(slug) => Route::SharedCode {
+                slug: slug.clone(),
+                display,
+            },
+        }
+    }
+}
+
+// 课程的签到码页面，标识无效时返回 None
+pub fn class_code_route(target: &ClassCodeTarget, display: CodeDisplayOptions) -> Option<Route> {
+    match target {
+        ClassCodeTarget::Id(id) => (*id > 0).then_some(Route::ClassCode {
+            id: ClassId(*id),
+            display,
+        }),
+        ClassCodeTarget::Slug(slug) => slug
+            .parse()
+            .ok()
+            .map(|slug| Route::SharedCode { slug, display }),
+    }
+}
+
 #[component]
-pub fn Code(code_gen_option: String, id: String) -> Element {
-    let class_data = use_signal(|| None::<ClassData>);
-    let error_message = use_signal(|| String::new());
-    let loading = use_signal(|| true); // 初始状态为加载中
-
-    let time_message = use_signal(|| String::new());
-    let mut help_message = use_signal(|| String::new());
-
-    // 根据 code_gen_option 决定如何处理 id
-    let _site_id = match code_gen_option.as_str() {
-        "i" => id.clone(),
-        "id" => id.clone(),
-        other => {
-            return rsx! {
-                PageNotFound { segments: vec![other.to_string(), id.clone()] }
-            };
+pub fn ClassCode(id: ClassId, display: CodeDisplayOptions) -> Element {
+    rsx! {
+        Code { locator: ClassLocator::Id(id), display }
+    }
+}
+
+#[component]
+pub fn SharedCode(slug: ShareSlug, display: CodeDisplayOptions) -> Element {
+    rsx! {
+        Code { locator: ClassLocator::Slug(slug), display }
+    }
+}
+
+// 旧的按 site_id 访问的地址，查到课程后用规范地址替换当前地址，保留显示选项
+#[component]
+pub fn SiteCode(site_id: SiteId, display: CodeDisplayOptions) -> Element {
+    let navigator = use_navigator();
+    let mut error_message = use_signal(String::new);
+    let mut sign_in_required = use_signal(|| false);
+
+    use_effect(move || {
+        let site_id = site_id.clone();
+        spawn(async move {
+            match get_class_code_target(site_id.to_string()).await {
+                Ok(Some(target)) => match class_code_route(&target, display) {
+                    Some(route) => {
+                        navigator.replace(route);
+                    }
+                    None => error_message.set("未找到指定的课程数据".to_string()),
+                },
+                Ok(None) => error_message.set("未找到指定的课程数据".to_string()),
+                // 关闭按 id 访问后只能跳转到分享链接，分享链接需要登录才能查看
+                Err(ServerFnError::WrappedServerError(ApiError::Unauthorized)) => {
+                    sign_in_required.set(true);
+                    error_message.set("该课程只能通过分享链接访问，登录后才能跳转".to_string());
+                }
+                Err(e) => error_message.set(format!("加载数据失败: {}", api_error_message(&e))),
+            }
+        });
+    });
+
+    rsx! {
+        div { id: "code", class: "home-container",
+            div { class: "home-scanner-container",
+                if error_message().is_empty() {
+                    div { class: "home-warning-message",
+                        p { "正在跳转..." }
+                    }
+                } else {
+                    div { class: "home-error-message",
+                        h3 { "错误:" }
+                        p { "{error_message()}" }
+                    }
+                    if sign_in_required() {
+                        Link { to: Route::SignIn {}, class: "class-detail-link", "登录" }
+                    }
+                }
+            }
         }
-    };
+    }
+}
+
+#[component]
+fn Code(locator: ClassLocator, display: CodeDisplayOptions) -> Element {
+    let mut class_data = use_signal(|| None::<ClassData>);
+    let mut error_message = use_signal(String::new);
+    let mut loading = use_signal(|| true); // 初始状态为加载中
+
+    let mut time_message = use_signal(String::new);
+    let mut help_message = use_signal(String::new);
 
     // 加载数据
+    let reload_locator = locator.clone();
+    let link_locator = locator.clone();
+    let events_locator = locator.clone();
     use_effect(move || {
-        let id = id.clone();
-        let code_gen_option = code_gen_option.clone();
-        let mut class_data = class_data.clone();
-        let mut error_message = error_message.clone();
-        let mut loading = loading.clone();
-        
+        let locator = locator.clone();
+
         spawn_local(async move {
             loading.set(true);
-            let result = match code_gen_option.as_str() {
-                "i" => {
-                    if let Ok(record_id) = id.parse::<i64>() {
-                        get_class_data_by_id(record_id).await
-                    } else {
-                        Err(ServerFnError::new(format!("无效的ID格式: {}", id)))
-                    }
-                }
-                "id"  => get_class_data(id).await,
-                _ => {
-                    // This case is already handled by the PageNotFound component, but we can add a safeguard.
-                    Err(ServerFnError::new("无效的选项".to_string()))
-                }
-            };
+            let result = locator.load().await;
 
             match result {
                 Ok(Some(data)) => {
@@ -91,45 +181,82 @@ pub fn Code(code_gen_option: String, id: String) -> Element {
                     error_message.set("未找到指定的课程数据".to_string());
                 }
                 Err(e) => {
-                    error_message.set(format!("加载数据失败: {:?}", e));
+                    error_message.set(format!("加载数据失败: {}", api_error_message(&e)));
                 }
             }
             loading.set(false);
         });
     });
 
+    // 加载到课程后订阅更新，有人扫到新的签到码时立即显示。
+    // 连接（包括断线重连）成功时重新加载一次，补上未连接期间的更新
+    let mut subscription = use_signal(|| None::<ClassUpdateSubscription>);
+    use_effect(move || {
+        if class_data.read().is_none() || subscription.peek().is_some() {
+            return;
+        }
+        let locator = events_locator.clone();
+        let path = locator.events_path();
+        subscription.set(Some(subscribe_class_updates(path, move |update| {
+            match update {
+                ClassUpdate::Connected => {
+                    let locator = locator.clone();
+                    spawn_local(async move {
+                        if let Ok(Some(data)) = locator.load().await {
+                            class_data.set(Some(data));
+                        }
+                    });
+                }
+                ClassUpdate::Updated(data) => class_data.set(Some(data)),
+            }
+        })));
+    });
+    // 页面卸载时关闭事件流并停止下面的定时刷新
+    let mut reload_task = use_signal(|| None::<Task>);
+    use_drop(move || {
+        if let Ok(mut subscription) = subscription.try_write() {
+            subscription.take();
+        }
+        if let Some(task) = reload_task.try_write().ok().and_then(|mut task| task.take()) {
+            task.cancel();
+        }
+    });
+
+    let qr_options = QrOptions {
+        size: display.qr_size(),
+        ..QrOptions::default()
+    };
     let mut img_src = use_resource(move || {
         let class_data = class_data();
         async move {
             if let Some(data) = class_data {
-                let qr_data = format_signing_code(&data);
-                // 简单的 URL 编码，替换特殊字符
-                let encoded_data = qr_data
-                    .replace("|", "%7C")
-                    .replace("&", "%26")
-                    .replace("=", "%3D");
-                format!(
-                    "https://api.2dcode.biz/v1/create-qr-code?data={}&size=256x256",
-                    encoded_data
-                )
+                let qr_data = format_signing_code(&data, &client_clock());
+                // 在本地生成二维码，不再依赖第三方接口
+                render_qr_data_url(&qr_data, &qr_options).unwrap_or_else(|e| {
+                    web_sys::console::error_1(&format!("二维码生成失败: {}", e).into());
+                    String::new()
+                })
             } else {
                 "".to_string()
             }
         }
     });
 
-    // 每2秒自动刷新二维码
+    // 按显示选项的间隔（默认2秒）自动刷新二维码。
+    // 每分钟重新加载课程数据，更新过期状态，事件流不可用时也能看到新的签到码
+    let refresh_secs = display.refresh_secs();
+    let reload_ticks = (CLASS_DATA_RELOAD_SECS / refresh_secs).max(1);
     use_effect(move || {
-        let mut img_src = img_src.clone();
-        let mut time_message = time_message.clone();
-        let class_data = class_data.clone();
-        spawn_local(async move {
-            loop {
-                // 等待2秒
+        let locator = reload_locator.clone();
+        let task = spawn(async move {
+            for tick in 1u32.. {
                 let promise = js_sys::Promise::new(&mut |resolve, _| {
                     web_sys::window()
                         .unwrap()
-                        .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 2000)
+                        .set_timeout_with_callback_and_timeout_and_arguments_0(
+                            &resolve,
+                            (refresh_secs * 1000) as i32,
+                        )
                         .unwrap();
                 });
                 wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
@@ -139,19 +266,38 @@ pub fn Code(code_gen_option: String, id: String) -> Element {
                     if let Some(created_time) = data.last_created_time.as_ref() {
                         time_message.set(format!(
                             "原码创建于{}分钟前",
-                            time_diff_from_now(created_time)
+                            time_diff_from_now(&client_clock(), created_time)
                         ));
                     }
                 }
 
+                if tick % reload_ticks == 0 {
+                    if let Ok(Some(data)) = locator.load().await {
+                        class_data.set(Some(data));
+                    }
+                }
+
                 // 刷新二维码
                 img_src.restart();
             }
         });
+        if let Some(previous) = reload_task.replace(Some(task)) {
+            previous.cancel();
+        }
     });
 
+    // 指定了二维码大小时，放宽页面和图片的默认宽度限制
+    let container_style = display
+        .size
+        .map(|size| format!("max-width: max(350px, {}px);", size + 60))
+        .unwrap_or_default();
+    let image_style = display
+        .size
+        .map(|size| format!("width: {}px; max-width: 100%; max-height: none;", size))
+        .unwrap_or_default();
+
     rsx! {
-        div { id: "code", class: "home-container",
+        div { id: "code", class: "home-container", style: "{container_style}",
             div { class: "home-scanner-container",
                 h1 { class: "home-title", "CHECK NOW!" }
 
@@ -159,31 +305,45 @@ pub fn Code(code_gen_option: String, id: String) -> Element {
                     div { class: "home-warning-message",
                         p { "正在加载数据..." }
                     }
-                } else if let Some(_data) = class_data() {
+                } else if let Some(data) = class_data() {
                     div {
+                        // 课程已过期时，签到码很可能已失效
+                        if data.is_expired {
+                            div { class: "home-error-message",
+                                h3 { "签到码已过期" }
+                                p {
+                                    if let Some(last_time) = data.last_created_time.as_ref() {
+                                        "最新的签到码生成于 {last_time.to_local_string()}，已超过有效期。"
+                                    } else {
+                                        "该课程已被标记为过期。"
+                                    }
+                                }
+                                p { "请重新扫描最新的签到码后再使用。" }
+                            }
+                        }
+
                         // 显示课程信息
-                        // div { class: "course-info",
-                        //     p { "课程: {data.class_name}" }
-                        //     p { "班级: {data.classes}" }
-                        //     if let Some(last_time) = data.last_created_time.as_ref() {
-                        //         p { "上次创建: {last_time}" }
-                        //     }
-                        //     p {
-                        //         if data.is_expired {
-                        //             "状态: 已过期"
-                        //         } else {
-                        //             "状态: 有效"
-                        //         }
-                        //     }
-                        // }
+                        div { class: "course-info",
+                            p { "课程: {data.class_name}" }
+                            p { "班级: {data.classes}" }
+                            if let Ok(site_id) = data.site_id.parse::<SiteId>() {
+                                Link {
+                                    to: Route::ClassDetail { site_id },
+                                    class: "class-detail-link",
+                                    "查看详情 / 编辑"
+                                }
+                            }
+                        }
 
                         // 二维码显示区域
-                        div { class: "qr-code-container",
+                        div {
+                            class: if data.is_expired { "qr-code-container qr-code-expired" } else { "qr-code-container" },
                             if let Some(src) = img_src() {
                                 if !src.is_empty() {
                                     img {
                                         src: "{src}",
                                         class: "qr-code-image",
+                                        style: "{image_style}",
                                         alt: "签到二维码",
                                     }
                                 } else {
@@ -207,9 +367,12 @@ pub fn Code(code_gen_option: String, id: String) -> Element {
                             }
                             button {
                                 onclick: move |_| {
+                                    // 复制当前页面的地址，保留显示选项
                                     if let Some(window) = web_sys::window() {
-                                        if let Some(location) = window.location().href().ok() {
-                                            copy_to_clipboard(&location);
+                                        if let Ok(origin) = window.location().origin() {
+                                            let route = link_locator.route(display).to_string();
+                                            let path = route.trim_end_matches('?');
+                                            copy_to_clipboard(&format!("{}{}", origin, path));
                                         }
                                     }
                                     help_message
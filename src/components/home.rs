@@ -1,10 +1,10 @@
-use crate::models::SigningCode;
+use crate::components::ScanVerdictView;
+use crate::models::ScanSource;
 use dioxus::prelude::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 // use crate::routes::Route;
 use crate::js_bindings::*;
-use crate::utils::parse_signing_code;
 use crate::utils::*;
 
 #[derive(Clone)]
@@ -18,99 +18,50 @@ struct ZoomCapability {
 // 新增一个统一处理二维码数据的异步函数
 async fn handle_qr_code_data(
     qr_data: String,
-    mut qr_result: Signal<String>,
-    mut signing_code: Signal<Option<SigningCode>>,
-    mut invalid_qr_message: Signal<String>,
-    mut error_message: Signal<String>,
+    source: ScanSource,
+    mut verdict: Signal<Option<ScanVerdict>>,
     mut image_upload_message: Signal<String>,
 ) {
-    if let Some(parsed_code) = parse_signing_code(&qr_data) {
-        // 验证二维码是否有效
-        let current_time_diff = time_diff_from_now(&parsed_code.create_time);
-        
-        // 检查二维码是否超过200分钟
-        if current_time_diff > 200 {
-            invalid_qr_message.set(format!("二维码已过期（超过{}分钟）", current_time_diff));
+    image_upload_message.set(String::new());
+
+    // 先在本地校验格式和有效期，与已保存签到码的比较由服务器在保存时完成
+    let clock = FixedClock(client_clock().now());
+    let result = match validate_scan(&qr_data, None, &clock) {
+        Ok(_) => {
+            verdict.set(None);
+            image_upload_message.set("二维码识别成功，正在保存...".to_string());
+            // 服务器会重新解析并校验，同时记录扫码日志
+            let result = match save_signing_code(qr_data.clone(), source).await {
+                Ok(result) => result,
+                Err(e) => ScanVerdict::StorageFailure(api_error_message(&e)),
+            };
             image_upload_message.set(String::new());
-            let _ = log_scan_result(qr_data.clone()).await;
-            return;
+            result
         }
-        
-        // 检查数据库中是否已有相同site_id的记录
-        match get_class_data(parsed_code.site_id.clone()).await {
-            Ok(Some(existing_data)) => {
-                // 如果数据库中存在记录，检查创建时间
-                if let Some(existing_time) = existing_data.last_created_time {
-                    let existing_time_diff = time_diff_from_now(&existing_time);
-                    // 如果当前二维码晚于数据库中的记录，则无效
-                    if current_time_diff > existing_time_diff {
-                        invalid_qr_message.set(format!("二维码无效（2）"));
-                        image_upload_message.set(String::new());
-                        let _ = log_scan_result(qr_data.clone()).await;
-                        return;
-                    }
-                }
-            }
-            Ok(None) => {
-                // 数据库中没有记录，继续处理
-            }
-            Err(e) => {
-                error_message.set(format!("数据库查询失败: {:?}", e));
-                let _ = log_scan_result(qr_data.clone()).await;
-                return;
-            }
+        Err(result) => {
+            let _ = log_scan_result(qr_data.clone(), source).await;
+            result
         }
-        
-        // 内容正确：保存并跳转
-        qr_result.set(qr_data.clone());
-        signing_code.set(Some(parsed_code.clone()));
-        image_upload_message.set("二维码识别成功，正在跳转...".to_string());
-        invalid_qr_message.set(String::new());
-        error_message.set(String::new());
+    };
 
-        // 异步保存扫码数据
-        match save_signing_code(parsed_code.clone()).await {
-            Ok(_) => {
-                // 保存成功后，从数据库获取id并跳转
-                match get_class_id(parsed_code.site_id.clone()).await {
-                    Ok(Some(id)) => {
-                        let url = format!("/i/{}", id);
-                        if let Some(window) = web_sys::window() {
-                            if let Err(e) = window.location().set_href(&url) {
-                                web_sys::console::error_1(&format!("跳转失败: {:?}", e).into());
-                            }
-                        }
-                    }
-                    Ok(None) => {
-                        error_message.set("未找到新保存的课程ID".to_string());
-                    }
-                    Err(e) => {
-                        error_message.set(format!("获取课程ID失败: {:?}", e));
-                    }
-                }
-            }
-            Err(e) => {
-                error_message.set(format!("保存失败: {:?}", e));
+    // 保存成功后跳转到课程的签到码页面
+    if let Some(url) = result.class_path() {
+        if let Some(window) = web_sys::window() {
+            if let Err(e) = window.location().set_href(&url) {
+                web_sys::console::error_1(&format!("跳转失败: {:?}", e).into());
             }
         }
-    } else {
-        // 内容错误
-        invalid_qr_message.set(format!("无效格式"));
-        image_upload_message.set(String::new());
     }
-
-    // 将扫描结果保存到scanlogs.txt
-    let _ = log_scan_result(qr_data.clone()).await;
+    verdict.set(Some(result));
 }
 
 #[component]
 pub fn Home() -> Element {
     let mut scanning = use_signal(|| false);
-    let qr_result = use_signal(|| String::new());
-    let signing_code = use_signal(|| None::<SigningCode>);
-    let mut error_message = use_signal(|| String::new());
-    let mut invalid_qr_message = use_signal(|| String::new());
-    let mut help_message = use_signal(|| String::new());
+    let verdict = use_signal(|| None::<ScanVerdict>);
+    let mut error_message = use_signal(String::new);
+    let mut invalid_qr_message = use_signal(String::new);
+    let mut help_message = use_signal(String::new);
     let mut zoom: Signal<ZoomCapability> = use_signal(|| ZoomCapability {
         level: 1.0,
         min: 1.0,
@@ -118,18 +69,12 @@ pub fn Home() -> Element {
         step: 1.0,
     });
     // 图片上传相关状态
-    let image_upload_message = use_signal(|| String::new());
+    let mut image_upload_message = use_signal(String::new);
 
     // QR code detection setup - 摄像头扫描
     use_effect(move || {
         let window = web_sys::window().unwrap();
         let closure = Closure::wrap(Box::new({
-            let qr_result = qr_result.clone();
-            let signing_code = signing_code.clone();
-            let mut scanning = scanning.clone();
-            let invalid_qr_message = invalid_qr_message.clone();
-            let error_message = error_message.clone();
-            let image_upload_message = image_upload_message.clone();
 
             move |event: web_sys::Event| {
                 // Use js_sys::Reflect to access custom event properties
@@ -139,21 +84,12 @@ pub fn Home() -> Element {
                             if let Ok(data) = js_sys::Reflect::get(&detail_obj, &"data".into()) {
                                 if let Some(qr_data) = data.as_string() {
                                     // 验证二维码内容
-                                    let qr_data_clone = qr_data.clone();
-                                    let qr_result_clone = qr_result.clone();
-                                    let signing_code_clone = signing_code.clone();
-                                    let invalid_qr_message_clone = invalid_qr_message.clone();
-                                    let error_message_clone = error_message.clone();
-                                    let image_upload_message_clone = image_upload_message.clone();
-
                                     spawn_local(async move {
                                         handle_qr_code_data(
-                                            qr_data_clone,
-                                            qr_result_clone,
-                                            signing_code_clone,
-                                            invalid_qr_message_clone,
-                                            error_message_clone,
-                                            image_upload_message_clone,
+                                            qr_data,
+                                            ScanSource::Camera,
+                                            verdict,
+                                            image_upload_message,
                                         )
                                         .await;
                                     });
@@ -179,11 +115,6 @@ pub fn Home() -> Element {
     use_effect(move || {
         let window = web_sys::window().unwrap();
         let closure = Closure::wrap(Box::new({
-            let qr_result = qr_result.clone();
-            let signing_code = signing_code.clone();
-            let mut invalid_qr_message = invalid_qr_message.clone();
-            let error_message = error_message.clone();
-            let mut image_upload_message = image_upload_message.clone();
 
             move |event: web_sys::Event| {
                 if let Ok(event_obj) = event.dyn_into::<js_sys::Object>() {
@@ -199,23 +130,12 @@ pub fn Home() -> Element {
                                     {
                                         if let Some(qr_data) = data.as_string() {
                                             // 使用统一的函数处理二维码数据
-                                            let qr_data_clone = qr_data.clone();
-                                            let qr_result_clone = qr_result.clone();
-                                            let signing_code_clone = signing_code.clone();
-                                            let invalid_qr_message_clone =
-                                                invalid_qr_message.clone();
-                                            let error_message_clone = error_message.clone();
-                                            let image_upload_message_clone =
-                                                image_upload_message.clone();
-
                                             spawn_local(async move {
                                                 handle_qr_code_data(
-                                                    qr_data_clone,
-                                                    qr_result_clone,
-                                                    signing_code_clone,
-                                                    invalid_qr_message_clone,
-                                                    error_message_clone,
-                                                    image_upload_message_clone,
+                                                    qr_data,
+                                                    ScanSource::Upload,
+                                                    verdict,
+                                                    image_upload_message,
                                                 )
                                                 .await;
                                             });
@@ -256,10 +176,6 @@ pub fn Home() -> Element {
         invalid_qr_message.set(String::new());
         help_message.set(String::new()); // 清除帮助消息
 
-        let mut error_message = error_message.clone();
-        let mut scanning = scanning.clone();
-        let mut zoom = zoom.clone();
-
         spawn_local(async move {
             // Wait a bit for DOM to update
             let promise = js_sys::Promise::new(&mut |resolve, _| {
@@ -343,7 +259,6 @@ pub fn Home() -> Element {
 
     // 页面加载2秒后显示帮助提示
     use_effect(move || {
-        let mut help_message = help_message.clone();
         spawn_local(async move {
             // 等待2秒
             let promise = js_sys::Promise::new(&mut |resolve, _| {
@@ -362,7 +277,6 @@ pub fn Home() -> Element {
     // 监听扫描状态，开始扫描2秒后显示第二个帮助提示
     use_effect(move || {
         let scanning = scanning();
-        let mut help_message = help_message.clone();
 
         if scanning {
             spawn_local(async move {
@@ -393,11 +307,21 @@ pub fn Home() -> Element {
                     scanning,
                     on_start: start_scanning,
                     on_stop: stop_scanning,
+                    on_paste: move |qr_data: String| {
+                        spawn_local(async move {
+                            handle_qr_code_data(
+                                qr_data,
+                                ScanSource::Paste,
+                                verdict,
+                                image_upload_message,
+                            )
+                            .await;
+                        });
+                    },
                 }
 
                 MessageDisplay {
-                    qr_result,
-                    signing_code,
+                    verdict,
                     invalid_qr_message,
                     error_message,
                     help_message,
@@ -509,7 +433,11 @@ fn ScanControls(
     scanning: Signal<bool>,
     on_start: EventHandler<()>,
     on_stop: EventHandler<()>,
+    on_paste: EventHandler<String>,
 ) -> Element {
+    // 手动粘贴的签到码内容
+    let mut pasted = use_signal(String::new);
+
     rsx! {
         div { class: "home-scanner-controls",
 
@@ -523,16 +451,14 @@ fn ScanControls(
                             accept: "image/*",
                             style: "display: none;",
                             oninput: move |_| {
-                                let js_code = format!(
-                                    r#"
+                                let js_code = r#"
                                                                                     const input = document.getElementById('image-upload');
                                                                                     const file = input.files[0];
-                                                                                    if (file) {{
+                                                                                    if (file) {
                                                                                         window.handleImageUpload(file);
-                                                                                    }}
-                                                                                "#,
-                                );
-                                let _ = js_sys::eval(&js_code);
+                                                                                    }
+                                                                                "#;
+                                let _ = js_sys::eval(js_code);
                             },
                         }
                         button {
@@ -550,6 +476,25 @@ fn ScanControls(
                             "上传图片"
                         }
                     }
+
+                    div { class: "home-paste-group",
+                        input {
+                            r#type: "text",
+                            class: "home-paste-input",
+                            placeholder: "或粘贴签到码内容",
+                            value: "{pasted}",
+                            oninput: move |event| pasted.set(event.value()),
+                        }
+                        button {
+                            class: "home-button-primary",
+                            disabled: pasted().trim().is_empty(),
+                            onclick: move |_| {
+                                on_paste.call(pasted().trim().to_string());
+                                pasted.set(String::new());
+                            },
+                            "提交"
+                        }
+                    }
                 }
             } else {
                 button {
@@ -565,8 +510,7 @@ fn ScanControls(
 // 消息显示组件
 #[component]
 fn MessageDisplay(
-    qr_result: Signal<String>,
-    signing_code: Signal<Option<SigningCode>>,
+    verdict: Signal<Option<ScanVerdict>>,
     invalid_qr_message: Signal<String>,
     error_message: Signal<String>,
     help_message: Signal<String>,
@@ -581,18 +525,12 @@ fn MessageDisplay(
                 }
             }
 
-            // 成功消息
-            if !qr_result().is_empty() {
-                div { class: "home-success-message",
-                    if signing_code().is_some() {
-                        div {
-                            h4 { "扫描成功，正在跳转..." }
-                        }
-                    }
-                }
+            // 扫码结论
+            if let Some(verdict) = verdict() {
+                ScanVerdictView { verdict }
             }
 
-            // 无效二维码消息
+            // 图片识别失败消息
             if !invalid_qr_message().is_empty() {
                 div { class: "home-warning-message",
                     h4 { "{invalid_qr_message()}" }
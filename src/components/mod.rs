@@ -1,11 +1,19 @@
 // 声明所有组件模块
+pub mod class_detail;
+pub mod class_list;
 pub mod code;
 pub mod home;
 pub mod nav;
 pub mod pagenotfound;
+pub mod scan_verdict;
+pub mod sign_in;
 
 // 重新导出所有组件，方便外部使用
-pub use code::Code;
+pub use class_detail::ClassDetail;
+pub use class_list::ClassList;
+pub use code::{class_code_route, ClassCode, SharedCode, SiteCode};
 pub use home::Home;
 pub use nav::Navbar;
-pub use pagenotfound::PageNotFound;
\ No newline at end of file
+pub use pagenotfound::PageNotFound;
+pub use scan_verdict::ScanVerdictView;
+pub use sign_in::SignIn;
\ No newline at end of file
@@ -9,6 +9,8 @@ pub fn Navbar() -> Element {
             Link { to: Route::Home {},
                 h2 { "BUPTricks" }
             }
+            Link { to: Route::ClassList {}, class: "navbar-link", "课程列表" }
+            Link { to: Route::SignIn {}, class: "navbar-link", "登录" }
         }
         Outlet::<Route> {}
     }
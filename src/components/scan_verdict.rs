@@ -0,0 +1,48 @@
+use crate::utils::{ScanVerdict, SIGNING_CODE_TTL_MINUTES};
+use dioxus::prelude::*;
+
+// 显示一次扫码的结论
+#[component]
+pub fn ScanVerdictView(verdict: ScanVerdict) -> Element {
+    let class = match &verdict {
+        ScanVerdict::StorageFailure(_) => "home-error-message",
+        verdict if verdict.is_accepted() => "home-success-message",
+        _ => "home-warning-message",
+    };
+    let detail = match &verdict {
+        ScanVerdict::AcceptedNew { saved_at, .. }
+        | ScanVerdict::AcceptedUpdated {
+            saved_at,
+            new_lesson: false,
+            ..
+        } => format!("保存于 {}，正在跳转...", saved_at.to_local_string()),
+        ScanVerdict::AcceptedUpdated {
+            saved_at,
+            new_lesson: true,
+            ..
+        } => format!(
+            "新的一节课已开始，保存于 {}，正在跳转...",
+            saved_at.to_local_string()
+        ),
+        ScanVerdict::Expired { age_minutes } => format!(
+            "二维码已生成 {} 分钟，超过 {} 分钟的有效期，请重新获取",
+            age_minutes, SIGNING_CODE_TTL_MINUTES
+        ),
+        ScanVerdict::Stale { scanned, stored } => {
+            format!(
+                "二维码生成于 {}，早于已保存的签到码（{}）",
+                scanned.to_local_string(),
+                stored.to_local_string()
+            )
+        }
+        ScanVerdict::BadFormat(e) => e.to_string(),
+        ScanVerdict::StorageFailure(e) => e.clone(),
+    };
+
+    rsx! {
+        div { class,
+            h4 { "{verdict.title()}" }
+            p { "{detail}" }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::utils::*;
+use crate::Route;
+use dioxus::prelude::*;
+
+// 输入管理员签发的访问令牌登录。令牌由服务器保存在 cookie 中，修改课程等写操作需要先登录
+#[component]
+pub fn SignIn() -> Element {
+    let mut token_input = use_signal(String::new);
+    let mut submitting = use_signal(|| false);
+    let mut error_message = use_signal(String::new);
+
+    let mut signed_in = use_resource(auth_status);
+
+    let submit = move |_| {
+        let token = token_input().trim().to_string();
+        if token.is_empty() {
+            error_message.set("请输入访问令牌".to_string());
+            return;
+        }
+        error_message.set(String::new());
+        submitting.set(true);
+        spawn(async move {
+            match sign_in(token).await {
+                Ok(()) => {
+                    token_input.set(String::new());
+                    signed_in.restart();
+                }
+                Err(ServerFnError::WrappedServerError(ApiError::Unauthorized)) => {
+                    error_message.set("访问令牌无效或已被吊销".to_string())
+                }
+                Err(e) => error_message.set(format!("登录失败: {}", api_error_message(&e))),
+            }
+            submitting.set(false);
+        });
+    };
+
+    let sign_out_click = move |_| {
+        error_message.set(String::new());
+        spawn(async move {
+            match sign_out().await {
+                Ok(()) => signed_in.restart(),
+                Err(e) => error_message.set(format!("退出失败: {}", api_error_message(&e))),
+            }
+        });
+    };
+
+    rsx! {
+        div { id: "sign-in", class: "home-container",
+            div { class: "home-scanner-container",
+                h1 { class: "home-title", "登录" }
+
+                match &*signed_in.read_unchecked() {
+                    None => rsx! {
+                        div { class: "home-warning-message",
+                            p { "正在检查登录状态..." }
+                        }
+                    },
+                    Some(Ok(true)) => rsx! {
+                        div { class: "home-success-message",
+                            p { "已登录，可以修改课程信息和管理分享链接。" }
+                        }
+                        div { class: "button-group",
+                            Link {
+                                to: Route::ClassList {},
+                                class: "class-detail-link",
+                                "前往课程列表"
+                            }
+                            button { class: "home-button-danger", onclick: sign_out_click, "退出登录" }
+                        }
+                    },
+                    Some(Ok(false)) => rsx! {
+                        div { class: "home-paste-group",
+                            input {
+                                r#type: "password",
+                                class: "home-paste-input",
+                                placeholder: "访问令牌",
+                                value: "{token_input}",
+                                oninput: move |event| token_input.set(event.value()),
+                            }
+                            button {
+                                class: "home-button-primary",
+                                disabled: submitting(),
+                                onclick: submit,
+                                if submitting() {
+                                    "登录中..."
+                                } else {
+                                    "登录"
+                                }
+                            }
+                        }
+                        p { class: "class-detail-empty", "访问令牌由管理员签发。" }
+                    },
+                    Some(Err(e)) => rsx! {
+                        div { class: "home-error-message",
+                            p { "无法获取登录状态: {api_error_message(e)}" }
+                        }
+                    },
+                }
+
+                if !error_message().is_empty() {
+                    div { class: "home-error-message",
+                        p { "{error_message()}" }
+                    }
+                }
+            }
+        }
+    }
+}
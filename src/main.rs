@@ -1,11 +1,12 @@
 use dioxus::prelude::*;
 mod components;
-use crate::components::{Code, Home, Navbar, PageNotFound};
 mod models;
-// mod routes;
+mod routes;
+use routes::Route;
 
-use crate::utils::{api::*, db, signing_code::*, time::*};
 mod js_bindings;
+#[cfg(feature = "server")]
+mod server;
 mod utils;
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -14,6 +15,26 @@ const QR_SCANNER_JS: Asset = asset!("/assets/qr-scanner.js");
 const QR_JS: Asset = asset!("/assets/jsQR.js");
 
 fn main() {
+    // 仅检查数据库迁移，不启动服务
+    #[cfg(feature = "server")]
+    if std::env::args().any(|arg| arg == "--migrate-dry-run") {
+        if let Err(e) = utils::db::dry_run_migrations() {
+            eprintln!("Migration dry run failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 签发或吊销访问令牌，不启动服务
+    #[cfg(feature = "server")]
+    if let Some(result) = utils::auth::run_token_command(&std::env::args().collect::<Vec<_>>()) {
+        if let Err(e) = result {
+            eprintln!("Token command failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // #[cfg(not(feature = "server"))]
     // {
     //     // 从环境变量中获取服务器地址
@@ -23,11 +44,19 @@ fn main() {
     //     };
     //     server_fn::client::set_server_url(server_url);
     // }
+
+    // 服务端需要在同一运行时中启动后台任务
+    #[cfg(feature = "server")]
+    server::launch(App);
+    #[cfg(not(feature = "server"))]
     dioxus::launch(App);
 }
 
 #[component]
 fn App() -> Element {
+    // 启动时与服务器校时，并定期重新校时
+    use_effect(utils::start_clock_sync);
+
     rsx! {
         document::Link { rel: "icon", href: FAVICON }
         document::Link { rel: "stylesheet", href: MAIN_CSS }
@@ -37,15 +66,3 @@ fn App() -> Element {
         Router::<Route> {}
     }
 }
-
-#[derive(Debug, Clone, Routable, PartialEq)]
-#[rustfmt::skip]
-pub enum Route {
-    #[layout(Navbar)]
-    #[route("/:..segments")]
-    PageNotFound { segments: Vec<String> },
-    #[route("/")]
-    Home {},
-    #[route("/:code_gen_option/:id")]
-    Code { code_gen_option: String, id: String},
-}
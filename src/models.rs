@@ -1,30 +1,200 @@
+use crate::utils::signing_code::ScanVerdict;
+use crate::utils::time::CheckTime;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 // 签到码结构体
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SigningCode {
     pub id: String,
     pub site_id: String,
-    pub create_time: String,
+    pub create_time: CheckTime,
     pub class_lesson_id: String,
 }
 
-// 课程数据结构体
+// 保存课程数据时是新建还是更新
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaveStatus {
+    Created,
+    Updated,
+}
+
+// 保存课程数据的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveResult {
+    pub id: i64,
+    pub status: SaveStatus,
+    pub saved_at: CheckTime,
+    // 本次扫码的课次是否第一次出现（新的一节课开始了）
+    pub new_lesson: bool,
+    // 分享链接 /s/:slug 使用的随机值，None 表示分享链接已停用。
+    // 读接口默认公开，ClassData 中不包含该值，只在这里和需要令牌的 get_share_slug 中返回
+    pub share_slug: Option<String>,
+}
+
+// 签到码页面使用的课程标识，见 get_class_code_target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClassCodeTarget {
+    // 课程记录的 id，对应 /i/:id
+    Id(i64),
+    // 分享 slug，对应 /s/:slug
+    Slug(String),
+}
+
+// 课程数据结构体，对应 courses 表，last_* 字段来自最新一次扫码
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ClassData {
+    pub id: i64,
     pub site_id: String,
     pub class_name: String,
     pub classes: String,
     pub last_checkwork_id: Option<String>,
     pub last_class_lesson_id: Option<String>,
-    pub last_created_time: Option<String>,
+    pub last_created_time: Option<CheckTime>,
     pub is_expired: bool,
+    pub updated_at: CheckTime,
 }
 
+// 课程列表的排序字段
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClassSort {
+    #[default]
+    UpdatedAt,
+    // 最新一次扫码的签到码生成时间，没有扫码记录的课程排在最前（升序）或最后（降序）
+    LastScan,
+    ClassName,
+    Id,
+}
 
+// 课程列表的查询条件，None 表示不限
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClassQuery {
+    pub is_expired: Option<bool>,
+    // 在课程名称和班级中查找的子串，区分大小写
+    pub search: Option<String>,
+    pub updated_since: Option<CheckTime>,
+    pub sort: ClassSort,
+    // 默认按排序字段降序
+    pub ascending: bool,
+    // 跳过的条数，默认 0
+    pub offset: Option<u32>,
+    // 默认 20 条，最多 100 条
+    pub limit: Option<u32>,
+}
+
+// 课程列表的一页，total 为符合条件的课程总数
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassPage {
+    pub classes: Vec<ClassData>,
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
 
 // #[derive(PartialEq)]
 // pub enum CodeGenOptions {
 //     Name(String),
 //     Id(String),
-// }
\ No newline at end of file
+// }
+
+// 课程的一次扫码记录
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub id: i64,
+    pub checkwork_id: Option<String>,
+    pub class_lesson_id: Option<String>,
+    pub created_time: Option<CheckTime>,
+    pub scanned_at: CheckTime,
+}
+
+// 课程的一个课次（一节课），由签到码中的 classLessonId 标识
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lesson {
+    pub id: i64,
+    pub class_lesson_id: String,
+    pub first_seen: CheckTime,
+    pub last_seen: CheckTime,
+    // 按第一次出现的顺序
+    pub checkwork_ids: Vec<String>,
+}
+
+// 签到码的输入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanSource {
+    Camera,
+    Upload,
+    Paste,
+}
+
+impl ScanSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanSource::Camera => "camera",
+            ScanSource::Upload => "upload",
+            ScanSource::Paste => "paste",
+        }
+    }
+}
+
+impl fmt::Display for ScanSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ScanSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "camera" => Ok(ScanSource::Camera),
+            "upload" => Ok(ScanSource::Upload),
+            "paste" => Ok(ScanSource::Paste),
+            _ => Err(format!("未知的输入方式: {}", s)),
+        }
+    }
+}
+
+// 一条扫码日志，客户端只在调用 query_scan_logs 时用到
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanLogEntry {
+    pub id: i64,
+    pub logged_at: CheckTime,
+    pub raw: String,
+    // 能解析出签到码时才有
+    pub site_id: Option<String>,
+    pub verdict: ScanVerdict,
+    pub source: ScanSource,
+    pub user_agent: Option<String>,
+}
+
+// 扫码日志查询条件，None 表示不限
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanLogQuery {
+    pub site_id: Option<String>,
+    // ScanVerdict::kind() 的值
+    pub verdict: Option<String>,
+    pub source: Option<ScanSource>,
+    pub since: Option<CheckTime>,
+    pub until: Option<CheckTime>,
+    // 默认 100 条，最多 1000 条
+    pub limit: Option<u32>,
+}
+
+// 限流中间件按接口统计的请求数，自服务启动以来
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitStats {
+    pub endpoint: String,
+    pub allowed: u64,
+    pub rejected: u64,
+}
@@ -0,0 +1,150 @@
+use crate::components::{
+    ClassCode, ClassDetail, ClassList, Home, Navbar, PageNotFound, SharedCode, SignIn, SiteCode,
+};
+use crate::utils::QrOptions;
+use dioxus::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Routable, PartialEq)]
+#[rustfmt::skip]
+pub enum Route {
+    #[layout(Navbar)]
+    #[route("/:..segments")]
+    PageNotFound { segments: Vec<String> },
+    #[route("/")]
+    Home {},
+    #[route("/login")]
+    SignIn {},
+    #[route("/classes")]
+    ClassList {},
+    #[route("/classes/:site_id")]
+    ClassDetail { site_id: SiteId },
+    #[route("/i/:id?:..display")]
+    ClassCode { id: ClassId, display: CodeDisplayOptions },
+    #[route("/s/:slug?:..display")]
+    SharedCode { slug: ShareSlug, display: CodeDisplayOptions },
+    // 旧地址，跳转到 /i/:id，关闭按 id 访问时跳转到 /s/:slug，保留显示选项
+    #[route("/id/:site_id?:..display")]
+    SiteCode { site_id: SiteId, display: CodeDisplayOptions },
+}
+
+// 课程记录的自增 id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassId(pub i64);
+
+impl fmt::Display for ClassId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ClassId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .ok()
+            .filter(|id| *id > 0)
+            .map(ClassId)
+            .ok_or_else(|| format!("无效的课程 ID: {}", s))
+    }
+}
+
+// 签到码中的 siteId，只包含数字
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteId(String);
+
+impl fmt::Display for SiteId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SiteId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(SiteId(s.to_string()))
+        } else {
+            Err(format!("无效的 siteId: {}", s))
+        }
+    }
+}
+
+// 分享链接中的 slug，格式与服务器生成的一致：24 位小写十六进制
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareSlug(String);
+
+const SHARE_SLUG_LEN: usize = 24;
+
+impl fmt::Display for ShareSlug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ShareSlug {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() == SHARE_SLUG_LEN && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            Ok(ShareSlug(s.to_string()))
+        } else {
+            Err(format!("无效的分享链接: {}", s))
+        }
+    }
+}
+
+// 二维码边长（像素）和刷新间隔（秒）的允许范围
+const QR_SIZE_RANGE: RangeInclusive<u32> = 128..=1024;
+const REFRESH_SECS_RANGE: RangeInclusive<u32> = 1..=60;
+const DEFAULT_REFRESH_SECS: u32 = 2;
+
+// 签到码页面的显示选项，来自查询参数，例如 /s/:slug?size=400&refresh=5。
+// 无法解析或超出范围的值被忽略，使用默认值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CodeDisplayOptions {
+    pub size: Option<u32>,
+    pub refresh: Option<u32>,
+}
+
+impl CodeDisplayOptions {
+    pub fn qr_size(&self) -> u32 {
+        self.size.unwrap_or(QrOptions::default().size)
+    }
+
+    pub fn refresh_secs(&self) -> u32 {
+        self.refresh.unwrap_or(DEFAULT_REFRESH_SECS)
+    }
+}
+
+impl From<&str> for CodeDisplayOptions {
+    fn from(query: &str) -> Self {
+        let mut options = CodeDisplayOptions::default();
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            let value = value.parse::<u32>().ok();
+            match key {
+                "size" => options.size = value.filter(|size| QR_SIZE_RANGE.contains(size)),
+                "refresh" => {
+                    options.refresh = value.filter(|secs| REFRESH_SECS_RANGE.contains(secs))
+                }
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
+// 只输出设置过的选项，全部为默认值时为空
+impl fmt::Display for CodeDisplayOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = [("size", self.size), ("refresh", self.refresh)]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| format!("{}={}", key, value)))
+            .collect();
+        write!(f, "{}", pairs.join("&"))
+    }
+}
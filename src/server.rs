@@ -0,0 +1,27 @@
+use dioxus::prelude::*;
+
+// 服务端入口。与 dioxus::launch 相同地提供页面和服务端函数，另外在同一个运行时中启动后台任务，
+// 提供课程更新的事件流，并按客户端 IP 限制接口请求频率
+pub fn launch(app: fn() -> Element) {
+    tokio::runtime::Runtime::new()
+        .expect("Failed to start tokio runtime")
+        .block_on(async move {
+            crate::utils::expiry::spawn_expiry_task();
+
+            let address = dioxus::cli_config::fullstack_address_or_localhost();
+            let router = crate::utils::class_events::class_events_router()
+                .serve_dioxus_application(ServeConfig::builder(), app)
+                .layer(axum::middleware::from_fn(crate::utils::rate_limit::rate_limit));
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .expect("Failed to bind server address");
+            println!("Listening on {}", address);
+            // 限流需要对端地址
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+                .await
+                .expect("Server error");
+        });
+}
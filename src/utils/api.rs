@@ -1,8 +1,16 @@
-use crate::models::{ClassData, SigningCode};
+use crate::models::{
+    ClassCodeTarget, ClassData, ClassPage, ClassQuery, Lesson, RateLimitStats, SaveResult, ScanLogEntry,
+    ScanLogQuery, ScanRecord, ScanSource,
+};
+use crate::utils::api_error::ApiError;
+use crate::utils::signing_code::ScanVerdict;
+use crate::utils::time::{AppTimeZone, CheckTime};
 #[cfg(feature = "server")]
-use crate::utils::db::get_db;
+use crate::utils::auth::{request_is_authorized, require_read_access, require_write_access};
 #[cfg(feature = "server")]
-use crate::utils::time::get_formatted_time;
+use crate::utils::class_events::publish_class_change;
+#[cfg(feature = "server")]
+use crate::utils::repository::{with_class_repository, ClassUpsert};
 use dioxus::prelude::*;
 
 #[server(endpoint = "save_scanned_code_data")]
@@ -12,268 +20,372 @@ pub async fn save_scanned_code_data(
     classes: Option<String>,
     checkwork_id: Option<String>,
     class_lesson_id: Option<String>,
-    created_time: Option<String>,
-) -> Result<String, ServerFnError> {
-    let current_time = get_formatted_time();
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let exists = conn
-        .query_row(
-            "SELECT COUNT(*) FROM class_data WHERE site_id = ?1",
-            rusqlite::params![site_id],
-            |row| row.get::<_, i64>(0),
-        )
-        .map_err(|e| ServerFnError::new(format!("Database query error: {}", e)))?;
-    if exists > 0 {
-        if checkwork_id.is_some() || class_lesson_id.is_some() || created_time.is_some() {
-            conn.execute(
-                "UPDATE class_data 
-                 SET last_checkwork_id = COALESCE(?1, last_checkwork_id), 
-                     last_class_lesson_id = COALESCE(?2, last_class_lesson_id), 
-                     last_created_time = COALESCE(?3, last_created_time),
-                     is_expired = 0,
-                     updated_at = ?4
-                 WHERE site_id = ?5",
-                rusqlite::params![
-                    checkwork_id,
-                    class_lesson_id,
-                    created_time,
-                    current_time,
-                    site_id
-                ],
-            )
-            .map_err(|e| ServerFnError::new(format!("Database update error: {}", e)))?;
-        }
-        if class_name.is_some() || classes.is_some() {
-            conn.execute(
-                "UPDATE class_data 
-                 SET class_name = COALESCE(?1, class_name),
-                     classes = COALESCE(?2, classes),
-                     updated_at = ?3
-                 WHERE site_id = ?4",
-                rusqlite::params![class_name, classes, current_time, site_id],
-            )
-            .map_err(|e| ServerFnError::new(format!("Database update error: {}", e)))?;
-        }
-        Ok(format!("课程签到信息已更新 - {}", current_time))
-    } else {
-        conn.execute(
-            "INSERT INTO class_data 
-             (site_id, class_name, classes, last_checkwork_id, last_class_lesson_id, last_created_time, is_expired, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8)",
-            rusqlite::params![
-                site_id,
-                class_name.unwrap_or_else(|| format!("Course_{}", site_id)),
-                classes.unwrap_or_else(|| "Unknown Class".to_string()),
+    created_time: Option<CheckTime>,
+) -> Result<SaveResult, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    let changed_site_id = site_id.clone();
+    let result = with_class_repository(move |repo| {
+        repo.upsert_scan(
+            &site_id,
+            ClassUpsert {
+                class_name,
+                classes,
                 checkwork_id,
                 class_lesson_id,
                 created_time,
-                current_time,
-                current_time
-            ]
-        ).map_err(|e| ServerFnError::new(format!("Database insert error: {}", e)))?;
-        Ok(format!("新课程签到信息已保存 - {}", current_time))
-    }
+            },
+        )
+    })
+    .await?;
+    publish_class_change(changed_site_id).await;
+    Ok(result)
 }
 
+// 保存扫到的原始签到码。服务器重新解析，并在写入事务中检查有效期以及是否早于已保存的签到码，
+// 不通过或保存失败时返回相应的结论而不写入
 #[server(endpoint = "save_signing_code")]
-pub async fn save_signing_code(signing_code: SigningCode) -> Result<String, ServerFnError> {
-    save_scanned_code_data(
-        signing_code.site_id,
-        None,
-        None,
-        Some(signing_code.id),
-        Some(signing_code.class_lesson_id),
-        Some(signing_code.create_time),
-    )
-    .await
+pub async fn save_signing_code(
+    raw: String,
+    source: ScanSource,
+) -> Result<ScanVerdict, ServerFnError<ApiError>> {
+    use crate::utils::signing_code::{validate_scan, validate_signing_code};
+    use crate::utils::time::FixedClock;
+
+    let clock = FixedClock(CheckTime::now());
+    let code = match validate_scan(&raw, None, &clock) {
+        Ok(code) => code,
+        Err(verdict) => {
+            record_scan(raw, source, verdict.clone()).await;
+            return Ok(verdict);
+        }
+    };
+    let changed_site_id = code.site_id.clone();
+    let result = with_class_repository(move |repo| {
+        repo.upsert_scan_checked(
+            &code.site_id,
+            ClassUpsert {
+                checkwork_id: Some(code.id.clone()),
+                class_lesson_id: Some(code.class_lesson_id.clone()),
+                created_time: Some(code.create_time),
+                ..Default::default()
+            },
+            &|stored_time| validate_signing_code(&code, stored_time, &clock),
+        )
+    })
+    .await;
+    let verdict = match result {
+        Ok(Ok(saved)) => {
+            publish_class_change(changed_site_id).await;
+            saved.into()
+        }
+        Ok(Err(verdict)) => verdict,
+        // 数据库错误只打印在服务器上，返回给客户端和写入日志的是通用提示
+        Err(e) => ScanVerdict::StorageFailure(ApiError::from(e).message()),
+    };
+    record_scan(raw, source, verdict.clone()).await;
+    Ok(verdict)
 }
 
-#[server]
-pub async fn log_scan_result(data: String) -> Result<(), ServerFnError> {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-    use chrono::Local;
+// 记录客户端本地校验未通过的扫码；通过本地校验的签到码由 save_signing_code 在服务器端记录。
+// 结论由服务器重新校验得出，不采用客户端的结论。服务器校验通过（例如客户端时钟不准）时
+// 该签到码并没有保存，不记录日志
+#[server(endpoint = "log_scan_result")]
+pub async fn log_scan_result(
+    raw: String,
+    source: ScanSource,
+) -> Result<(), ServerFnError<ApiError>> {
+    use crate::utils::signing_code::validate_scan;
+    use crate::utils::time::FixedClock;
 
-    let now = Local::now();
-    let timestamp = now.format("%Y-%m-%d %H:%M:%S");
-    let log_entry = format!("[{}] {}\n", timestamp, data);
+    let clock = FixedClock(CheckTime::now());
+    if let Err(verdict) = validate_scan(&raw, None, &clock) {
+        record_scan(raw, source, verdict).await;
+    }
+    Ok(())
+}
 
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("scanlogs.txt")
-        .map_err(|e| ServerFnError::new(format!("Failed to open log file: {}", e)))?;
+// 写入扫码日志。日志写入失败不影响扫码结果，只打印错误
+#[cfg(feature = "server")]
+async fn record_scan(raw: String, source: ScanSource, verdict: ScanVerdict) {
+    use crate::utils::scan_logs::{record_scan_log, NewScanLog};
+    use crate::utils::signing_code::parse_signing_code;
 
-    file.write_all(log_entry.as_bytes())
-        .map_err(|e| ServerFnError::new(format!("Failed to write to log file: {}", e)))?;
+    let user_agent = server_context()
+        .request_parts()
+        .headers
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let log = NewScanLog {
+        site_id: parse_signing_code(&raw).ok().map(|code| code.site_id),
+        raw,
+        verdict,
+        source,
+        user_agent,
+    };
+    if let Err(e) = record_scan_log(log).await {
+        eprintln!("Failed to record scan log: {}", e);
+    }
+}
 
-    Ok(())
+// 按条件查询扫码日志，按时间倒序。日志包含原始签到码和 User-Agent，只供管理员排查问题，需要令牌
+#[server(endpoint = "query_scan_logs")]
+pub async fn query_scan_logs(query: ScanLogQuery) -> Result<Vec<ScanLogEntry>, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    Ok(crate::utils::scan_logs::query_scan_logs(query).await?)
+}
+
+// 课程名称和班级的最大长度（字符数）
+pub const MAX_CLASS_FIELD_CHARS: usize = 50;
+
+// 校验课程名称或班级，返回去掉首尾空白后的值。客户端保存前和服务器写入前使用同一规则
+pub fn validate_class_field(label: &str, value: &str) -> Result<String, ApiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ApiError::Validation(format!("{}不能为空", label)));
+    }
+    if value.chars().count() > MAX_CLASS_FIELD_CHARS {
+        return Err(ApiError::Validation(format!(
+            "{}不能超过 {} 个字符",
+            label, MAX_CLASS_FIELD_CHARS
+        )));
+    }
+    Ok(value.to_string())
 }
 
+// 修改课程名称和班级，None 表示不修改该项，返回修改后的课程
 #[server(endpoint = "update_class_info")]
 pub async fn update_class_info(
     site_id: String,
     class_name: Option<String>,
     classes: Option<String>,
-) -> Result<String, ServerFnError> {
-    save_scanned_code_data(
-        site_id,
-        class_name,
-        classes,
-        None,
-        None,
-        None,
-    )
-    .await
+) -> Result<ClassData, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    if class_name.is_none() && classes.is_none() {
+        return Err(ApiError::Validation("没有要修改的内容".to_string()).into());
+    }
+    let class_name = class_name
+        .map(|value| validate_class_field("课程名称", &value))
+        .transpose()?;
+    let classes = classes
+        .map(|value| validate_class_field("班级", &value))
+        .transpose()?;
+    let changed_site_id = site_id.clone();
+    let updated = with_class_repository(move |repo| {
+        if repo.update_info(&site_id, class_name, classes)? {
+            repo.get_by_site_id(&site_id)
+        } else {
+            Ok(None)
+        }
+    })
+    .await?;
+    let updated = updated.ok_or_else(|| ApiError::NotFound("指定课程".to_string()))?;
+    publish_class_change(changed_site_id).await;
+    Ok(updated)
 }
 
 #[server(endpoint = "get_current_time")]
-pub async fn get_current_time() -> Result<String, ServerFnError> {
-    use chrono::{FixedOffset, Utc};
-    let china_tz = FixedOffset::east_opt(8 * 3600).unwrap();
-    let current_time = Utc::now()
-        .with_timezone(&china_tz)
-        .format("%Y-%m-%dT%H:%M:%S%.3f")
-        .to_string();
-    Ok(current_time)
+pub async fn get_current_time() -> Result<CheckTime, ServerFnError<ApiError>> {
+    Ok(CheckTime::now())
+}
+
+#[server(endpoint = "get_time_zone")]
+pub async fn get_time_zone() -> Result<AppTimeZone, ServerFnError<ApiError>> {
+    use crate::utils::time::app_time_zone;
+    Ok(app_time_zone())
 }
 
 #[server(endpoint = "get_class_data")]
-pub async fn get_class_data(site_id: String) -> Result<Option<ClassData>, ServerFnError> {
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, site_id, class_name, classes, last_checkwork_id, \
-                last_class_lesson_id, last_created_time, is_expired
-         FROM class_data 
-         WHERE site_id = ?1",
-        )
-        .map_err(|e| ServerFnError::new(format!("Database prepare error: {}", e)))?;
-    let class_data = stmt.query_row(rusqlite::params![site_id], |row| {
-        Ok(ClassData {
-            // id: row.get(0)?,
-            site_id: row.get(1)?,
-            class_name: row.get(2)?,
-            classes: row.get(3)?,
-            last_checkwork_id: row.get(4)?,
-            last_class_lesson_id: row.get(5)?,
-            last_created_time: row.get(6)?,
-            is_expired: row.get(7)?,
-        })
-    });
-    match class_data {
-        Ok(data) => Ok(Some(data)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(ServerFnError::new(format!("Database query error: {}", e))),
+pub async fn get_class_data(site_id: String) -> Result<Option<ClassData>, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    let mut data = with_class_repository(move |repo| repo.get_by_site_id(&site_id)).await?;
+    if !code_fields_visible().await? {
+        data.iter_mut().for_each(hide_code_fields);
+    }
+    Ok(data)
+}
+
+// 是否允许按自增 id 访问课程的环境变量，设为 "false" 或 "0" 时 /i/:id 不可用，
+// 签到码页面只能通过分享链接访问，没有令牌时读接口也不返回生成签到码所需的字段
+#[cfg(feature = "server")]
+const NUMERIC_CLASS_ROUTES_ENV: &str = "NUMERIC_CLASS_ROUTES";
+
+#[cfg(feature = "server")]
+pub(crate) fn numeric_class_routes_enabled() -> bool {
+    !matches!(
+        std::env::var(NUMERIC_CLASS_ROUTES_ENV).as_deref(),
+        Ok("false") | Ok("0")
+    )
+}
+
+// 关闭按 id 访问时，没有令牌的请求看不到生成签到码所需的签到 id 和课次 id，
+// 否则仍能从公开的课程列表拼出每个课程的签到码。持有分享链接的人照常能看到
+#[cfg(feature = "server")]
+async fn code_fields_visible() -> Result<bool, ApiError> {
+    if numeric_class_routes_enabled() {
+        return Ok(true);
     }
+    request_is_authorized().await
+}
+
+#[cfg(feature = "server")]
+fn hide_code_fields(data: &mut ClassData) {
+    data.last_checkwork_id = None;
+    data.last_class_lesson_id = None;
 }
 
 #[server(endpoint = "get_class_data_by_id")]
-pub async fn get_class_data_by_id(id: i64) -> Result<Option<ClassData>, ServerFnError> {
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, site_id, class_name, classes, last_checkwork_id, \
-                last_class_lesson_id, last_created_time, is_expired
-         FROM class_data 
-         WHERE id = ?1",
-        )
-        .map_err(|e| ServerFnError::new(format!("Database prepare error: {}", e)))?;
-    let class_data = stmt.query_row(rusqlite::params![id], |row| {
-        Ok(ClassData {
-            // id: row.get(0)?,
-            site_id: row.get(1)?,
-            class_name: row.get(2)?,
-            classes: row.get(3)?,
-            last_checkwork_id: row.get(4)?,
-            last_class_lesson_id: row.get(5)?,
-            last_created_time: row.get(6)?,
-            is_expired: row.get(7)?,
-        })
-    });
-    match class_data {
-        Ok(data) => Ok(Some(data)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(ServerFnError::new(format!("Database query error: {}", e))),
+pub async fn get_class_data_by_id(id: i64) -> Result<Option<ClassData>, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    // 关闭时与课程不存在的结果相同，不透露该 id 是否存在
+    if !numeric_class_routes_enabled() {
+        return Ok(None);
+    }
+    Ok(with_class_repository(move |repo| repo.get_by_id(id)).await?)
+}
+
+#[server(endpoint = "get_class_data_by_slug")]
+pub async fn get_class_data_by_slug(slug: String) -> Result<Option<ClassData>, ServerFnError<ApiError>> {
+    Ok(with_class_repository(move |repo| repo.get_by_share_slug(&slug)).await?)
+}
+
+// 课程的分享 slug，分享链接已停用时返回 None。分享链接本身就是访问凭据，只对持有令牌的用户可见
+#[server(endpoint = "get_share_slug")]
+pub async fn get_share_slug(site_id: String) -> Result<Option<String>, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    Ok(with_class_repository(move |repo| repo.get_share_slug(&site_id)).await?)
+}
+
+// 为课程生成新的分享链接，原来的分享链接失效，返回新的 slug
+#[server(endpoint = "rotate_share_slug")]
+pub async fn rotate_share_slug(site_id: String) -> Result<String, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    let changed_site_id = site_id.clone();
+    let slug = with_class_repository(move |repo| repo.rotate_share_slug(&site_id))
+        .await?
+        .ok_or_else(|| ApiError::NotFound("指定课程".to_string()))?;
+    publish_class_change(changed_site_id).await;
+    Ok(slug)
+}
+
+// 停用课程的分享链接，之后可用 rotate_share_slug 重新生成
+#[server(endpoint = "revoke_share_slug")]
+pub async fn revoke_share_slug(site_id: String) -> Result<(), ServerFnError<ApiError>> {
+    require_write_access().await?;
+    let changed_site_id = site_id.clone();
+    if with_class_repository(move |repo| repo.revoke_share_slug(&site_id)).await? {
+        publish_class_change(changed_site_id).await;
+        Ok(())
+    } else {
+        Err(ApiError::NotFound("指定课程".to_string()).into())
+    }
+}
+
+// 课程签到码页面的标识，用于从 /id/:site_id 跳转。允许按 id 访问时为课程 id，
+// 否则为分享 slug；分享 slug 与 get_share_slug 一样需要令牌，分享链接已停用时返回 None
+#[server(endpoint = "get_class_code_target")]
+pub async fn get_class_code_target(
+    site_id: String,
+) -> Result<Option<ClassCodeTarget>, ServerFnError<ApiError>> {
+    if numeric_class_routes_enabled() {
+        require_read_access().await?;
+        let id = with_class_repository(move |repo| repo.get_id_by_site_id(&site_id)).await?;
+        Ok(id.map(ClassCodeTarget::Id))
+    } else {
+        require_write_access().await?;
+        let slug = with_class_repository(move |repo| repo.get_share_slug(&site_id)).await?;
+        Ok(slug.map(ClassCodeTarget::Slug))
     }
 }
 
 #[server(endpoint = "get_class_id")]
-pub async fn get_class_id(site_id: String) -> Result<Option<i64>, ServerFnError> {
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let mut stmt = conn
-        .prepare("SELECT id FROM class_data WHERE site_id = ?1")
-        .map_err(|e| ServerFnError::new(format!("Database prepare error: {}", e)))?;
-    let result = stmt.query_row(rusqlite::params![site_id], |row| row.get(0));
-    match result {
-        Ok(id) => Ok(Some(id)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(ServerFnError::new(format!("Database query error: {}", e))),
+pub async fn get_class_id(site_id: String) -> Result<Option<i64>, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    Ok(with_class_repository(move |repo| repo.get_id_by_site_id(&site_id)).await?)
+}
+
+// 课程的扫码历史，最新的在前
+#[server(endpoint = "list_class_history")]
+pub async fn list_class_history(site_id: String) -> Result<Vec<ScanRecord>, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    let mut scans = with_class_repository(move |repo| repo.list_scans(&site_id)).await?;
+    if !code_fields_visible().await? {
+        for scan in scans.iter_mut() {
+            scan.checkwork_id = None;
+            scan.class_lesson_id = None;
+        }
+    }
+    Ok(scans)
+}
+
+// 课程出现过的课次，最近出现的在前。课次由签到 id 和课次 id 组成，
+// 看不到这两项时（见 code_fields_visible）需要令牌
+#[server(endpoint = "list_course_lessons")]
+pub async fn list_course_lessons(site_id: String) -> Result<Vec<Lesson>, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    if !code_fields_visible().await? {
+        return Err(ApiError::Unauthorized.into());
     }
+    Ok(with_class_repository(move |repo| repo.list_lessons(&site_id)).await?)
 }
 
+// 按条件分页列出课程，默认按更新时间倒序
 #[server(endpoint = "list_all_classes")]
-pub async fn list_all_classes() -> Result<Vec<ClassData>, ServerFnError> {
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, site_id, class_name, classes, last_checkwork_id,\
-                last_class_lesson_id, last_created_time, is_expired
-         FROM class_data 
-         ORDER BY updated_at DESC",
-        )
-        .map_err(|e| ServerFnError::new(format!("Database prepare error: {}", e)))?;
-    let class_iter = stmt
-        .query_map([], |row| {
-            Ok(ClassData {
-                // id: row.get(0)?,
-                site_id: row.get(1)?,
-                class_name: row.get(2)?,
-                classes: row.get(3)?,
-                last_checkwork_id: row.get(4)?,
-                last_class_lesson_id: row.get(5)?,
-                last_created_time: row.get(6)?,
-                is_expired: row.get(7)?,
-            })
-        })
-        .map_err(|e| ServerFnError::new(format!("Database query error: {}", e)))?;
-    let mut classes = Vec::new();
-    for class in class_iter {
-        classes.push(class.map_err(|e| ServerFnError::new(format!("Row parse error: {}", e)))?);
+pub async fn list_all_classes(query: ClassQuery) -> Result<ClassPage, ServerFnError<ApiError>> {
+    require_read_access().await?;
+    let mut page = with_class_repository(move |repo| repo.list(&query)).await?;
+    if !code_fields_visible().await? {
+        page.classes.iter_mut().for_each(hide_code_fields);
     }
-    Ok(classes)
+    Ok(page)
 }
 
+// 将课程标记为过期，返回修改后的课程
 #[server(endpoint = "mark_class_expired")]
-pub async fn mark_class_expired(site_id: String) -> Result<String, ServerFnError> {
-    let db = get_db();
-    let conn = db
-        .lock()
-        .map_err(|e| ServerFnError::new(format!("Database lock error: {}", e)))?;
-    let updated_rows = conn
-        .execute(
-            "UPDATE class_data SET is_expired = 1 WHERE site_id = ?1",
-            rusqlite::params![site_id],
-        )
-        .map_err(|e| ServerFnError::new(format!("Database update error: {}", e)))?;
-    if updated_rows > 0 {
-        Ok("课程已标记为过期".to_string())
-    } else {
-        Err(ServerFnError::new("未找到指定课程".to_string()))
+pub async fn mark_class_expired(site_id: String) -> Result<ClassData, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    let changed_site_id = site_id.clone();
+    let updated = with_class_repository(move |repo| {
+        if repo.mark_expired(&site_id)? {
+            repo.get_by_site_id(&site_id)
+        } else {
+            Ok(None)
+        }
+    })
+    .await?;
+    let updated = updated.ok_or_else(|| ApiError::NotFound("指定课程".to_string()))?;
+    publish_class_change(changed_site_id).await;
+    Ok(updated)
+}
+
+// 校验访问令牌，有效时保存到 cookie，之后浏览器发出的请求都会带上该令牌
+#[server(endpoint = "sign_in")]
+pub async fn sign_in(token: String) -> Result<(), ServerFnError<ApiError>> {
+    use crate::utils::auth::{set_access_token_cookie, token_is_valid};
+    let token = token.trim().to_string();
+    if !token_is_valid(token.clone()).await? {
+        return Err(ApiError::Unauthorized.into());
     }
+    set_access_token_cookie(&token);
+    Ok(())
+}
+
+// 清除保存令牌的 cookie
+#[server(endpoint = "sign_out")]
+pub async fn sign_out() -> Result<(), ServerFnError<ApiError>> {
+    crate::utils::auth::set_access_token_cookie("");
+    Ok(())
+}
+
+// 当前浏览器是否已登录（携带有效的令牌）
+#[server(endpoint = "auth_status")]
+pub async fn auth_status() -> Result<bool, ServerFnError<ApiError>> {
+    Ok(crate::utils::auth::request_is_authorized().await?)
+}
+
+// 限流中间件放行和拒绝的请求数，需要登录
+#[server(endpoint = "get_rate_limit_stats")]
+pub async fn get_rate_limit_stats() -> Result<Vec<RateLimitStats>, ServerFnError<ApiError>> {
+    require_write_access().await?;
+    Ok(crate::utils::rate_limit::rate_limit_stats())
 }
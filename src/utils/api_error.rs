@@ -0,0 +1,111 @@
+#[cfg(feature = "server")]
+use crate::utils::repository::RepositoryError;
+use dioxus::prelude::ServerFnError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// 服务端函数返回的错误。通过 Display 和 FromStr 在服务器与客户端之间传递，
+// 因此 Display 的输出必须能被 FromStr 原样解析回来；展示给用户的文字见 message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiError {
+    // 请求的资源不存在，内容为资源的描述，例如“课程”
+    NotFound(String),
+    // 参数不合法，内容为具体原因
+    Validation(String),
+    // 存储出错。具体的错误信息只打印在服务器上，不发送给客户端
+    Storage,
+    Unauthorized,
+    // 请求过于频繁，需等待的秒数
+    RateLimited { retry_after_secs: u64 },
+}
+
+impl ApiError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Validation(_) => "validation",
+            ApiError::Storage => "storage",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+        }
+    }
+
+    // 展示给用户的说明
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::NotFound(what) => format!("未找到{}", what),
+            ApiError::Validation(reason) => format!("输入有误：{}", reason),
+            ApiError::Storage => "服务器存储出错，请稍后重试".to_string(),
+            ApiError::Unauthorized => "没有权限执行此操作，请先登录".to_string(),
+            ApiError::RateLimited { retry_after_secs } => {
+                format!("请求过于频繁，请 {} 秒后重试", retry_after_secs)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotFound(detail) | ApiError::Validation(detail) => {
+                write!(f, "{}: {}", self.kind(), detail)
+            }
+            ApiError::Storage | ApiError::Unauthorized => write!(f, "{}", self.kind()),
+            ApiError::RateLimited { retry_after_secs } => {
+                write!(f, "{}: {}", self.kind(), retry_after_secs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl FromStr for ApiError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, detail) = match s.split_once(": ") {
+            Some((kind, detail)) => (kind, detail.to_string()),
+            None => (s, String::new()),
+        };
+        match kind {
+            "not_found" => Ok(ApiError::NotFound(detail)),
+            "validation" => Ok(ApiError::Validation(detail)),
+            "storage" => Ok(ApiError::Storage),
+            "unauthorized" => Ok(ApiError::Unauthorized),
+            "rate_limited" => detail
+                .parse()
+                .map(|retry_after_secs| ApiError::RateLimited { retry_after_secs })
+                .map_err(|_| format!("无效的等待时间: {}", detail)),
+            other => Err(format!("未知的错误类型: {}", other)),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl From<RepositoryError> for ApiError {
+    fn from(e: RepositoryError) -> Self {
+        eprintln!("{}", e);
+        match e {
+            RepositoryError::Storage(_) => ApiError::Storage,
+        }
+    }
+}
+
+// 让服务端函数中可以直接对仓库操作使用 ?
+#[cfg(feature = "server")]
+impl From<RepositoryError> for ServerFnError<ApiError> {
+    fn from(e: RepositoryError) -> Self {
+        ServerFnError::WrappedServerError(e.into())
+    }
+}
+
+// 服务端函数调用失败时展示给用户的说明。网络等非业务错误没有对应的 ApiError，统一说明
+pub fn api_error_message(e: &ServerFnError<ApiError>) -> String {
+    match e {
+        ServerFnError::WrappedServerError(e) => e.message(),
+        ServerFnError::Request(_) => "无法连接服务器，请检查网络后重试".to_string(),
+        other => format!("服务器响应异常：{}", other),
+    }
+}
@@ -0,0 +1,193 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::db::{get_db, initialize_database, run_blocking};
+use crate::utils::time::CheckTime;
+use axum::http::{header, HeaderMap, HeaderValue};
+use dioxus::prelude::server_context;
+use sha2::{Digest, Sha256};
+
+// 写操作（修改课程、标记过期、管理分享链接）需要访问令牌。令牌由管理员用
+// --issue-token 签发，数据库中只保存其 SHA-256。无论使用哪种数据存储后端，令牌都保存在 SQLite 中。
+// 首页扫码保存（save_signing_code）由服务器重新校验签到码，不需要令牌
+
+// 读操作是否公开的环境变量，设为 "false" 或 "0" 时读取课程数据也需要令牌。
+// 分享链接本身就是访问凭据，按分享 slug 读取始终公开
+const PUBLIC_READS_ENV: &str = "PUBLIC_READS";
+
+// 浏览器登录后保存令牌的 cookie
+const ACCESS_TOKEN_COOKIE: &str = "jw_access_token";
+const ACCESS_TOKEN_COOKIE_MAX_AGE_SECS: u64 = 180 * 24 * 60 * 60;
+
+// 令牌的随机字节数，编码为两倍长度的小写十六进制
+const ACCESS_TOKEN_BYTES: usize = 32;
+
+pub fn public_reads_enabled() -> bool {
+    !matches!(
+        std::env::var(PUBLIC_READS_ENV).as_deref(),
+        Ok("false") | Ok("0")
+    )
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// 请求携带的令牌：请求头 Authorization: Bearer <令牌>，或登录后设置的 cookie
+fn request_token(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    bearer.or_else(|| {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == ACCESS_TOKEN_COOKIE)
+            .map(|(_, token)| token.to_string())
+    })
+}
+
+// 令牌是否存在且未被吊销
+pub async fn token_is_valid(token: String) -> Result<bool, ApiError> {
+    if token.is_empty() {
+        return Ok(false);
+    }
+    let token_hash = hash_token(&token);
+    let pool = get_db();
+    run_blocking(move || {
+        pool.read(|conn| {
+            conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM access_tokens WHERE token_hash = ?1 AND revoked_at IS NULL)",
+                rusqlite::params![token_hash],
+                |row| row.get(0),
+            )
+        })
+    })
+    .await
+    .map_err(|e| {
+        eprintln!("Blocking task failed: {}", e);
+        ApiError::Storage
+    })?
+    .map_err(|e| {
+        eprintln!("Failed to check access token: {}", e);
+        ApiError::Storage
+    })
+}
+
+async fn has_valid_token(headers: &HeaderMap) -> Result<bool, ApiError> {
+    match request_token(headers) {
+        Some(token) => token_is_valid(token).await,
+        None => Ok(false),
+    }
+}
+
+// 请求必须携带有效的令牌
+pub async fn authorize(headers: &HeaderMap) -> Result<(), ApiError> {
+    if has_valid_token(headers).await? {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+// 读操作公开时不检查令牌
+pub async fn authorize_read(headers: &HeaderMap) -> Result<(), ApiError> {
+    if public_reads_enabled() {
+        return Ok(());
+    }
+    authorize(headers).await
+}
+
+fn current_request_headers() -> HeaderMap {
+    server_context().request_parts().headers.clone()
+}
+
+// 在写操作的服务端函数开头调用
+pub async fn require_write_access() -> Result<(), ApiError> {
+    authorize(&current_request_headers()).await
+}
+
+// 在读操作的服务端函数开头调用
+pub async fn require_read_access() -> Result<(), ApiError> {
+    authorize_read(&current_request_headers()).await
+}
+
+// 当前请求是否携带有效的令牌
+pub async fn request_is_authorized() -> Result<bool, ApiError> {
+    has_valid_token(&current_request_headers()).await
+}
+
+// 登录成功后在响应中设置保存令牌的 cookie，令牌为空时清除
+pub fn set_access_token_cookie(token: &str) {
+    let max_age = if token.is_empty() {
+        0
+    } else {
+        ACCESS_TOKEN_COOKIE_MAX_AGE_SECS
+    };
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        ACCESS_TOKEN_COOKIE, token, max_age
+    );
+    if let Ok(value) = HeaderValue::from_str(&cookie) {
+        server_context()
+            .response_parts_mut()
+            .headers
+            .append(header::SET_COOKIE, value);
+    }
+}
+
+fn new_access_token() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; ACCESS_TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// 签发令牌并返回明文，明文不会保存，只能在签发时看到
+pub fn issue_access_token(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let token = new_access_token()?;
+    let conn = initialize_database()?;
+    conn.execute(
+        "INSERT INTO access_tokens (name, token_hash, created_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![name, hash_token(&token), CheckTime::now()],
+    )?;
+    Ok(token)
+}
+
+// 吊销该名称下的所有令牌，返回吊销的个数
+pub fn revoke_access_tokens(name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let conn = initialize_database()?;
+    Ok(conn.execute(
+        "UPDATE access_tokens SET revoked_at = ?2 WHERE name = ?1 AND revoked_at IS NULL",
+        rusqlite::params![name, CheckTime::now()],
+    )?)
+}
+
+// 处理令牌管理命令：--issue-token <名称> 或 --revoke-token <名称>。
+// 参数中没有这两个命令时返回 None，照常启动服务
+pub fn run_token_command(args: &[String]) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    let position = args
+        .iter()
+        .position(|arg| arg == "--issue-token" || arg == "--revoke-token")?;
+    let command = args[position].as_str();
+    let Some(name) = args
+        .get(position + 1)
+        .filter(|name| !name.starts_with("--"))
+    else {
+        return Some(Err(format!("Usage: {} <name>", command).into()));
+    };
+    Some(match command {
+        "--issue-token" => issue_access_token(name).map(|token| {
+            println!("Issued access token for {}:", name);
+            println!("{}", token);
+        }),
+        _ => revoke_access_tokens(name).map(|count| {
+            println!("Revoked {} access token(s) for {}", count, name);
+        }),
+    })
+}
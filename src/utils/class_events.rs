@@ -0,0 +1,224 @@
+use crate::models::ClassData;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+
+// 课程更新事件流的路径前缀，按 id 订阅为 {前缀}/i/:id，按分享 slug 订阅为 {前缀}/s/:slug
+pub const CLASS_EVENTS_PATH: &str = "/api/class_events";
+
+// 断线重连的等待时间（毫秒），每次失败翻倍，连接成功后恢复初始值
+const RECONNECT_INITIAL_DELAY_MS: i32 = 1000;
+const RECONNECT_MAX_DELAY_MS: i32 = 30 * 1000;
+
+pub enum ClassUpdate {
+    // 连接（或重连）成功。断开期间的更新不会补发，需要重新加载一次
+    Connected,
+    Updated(ClassData),
+}
+
+// 订阅课程更新，断开后按退避间隔自动重连。返回值被丢弃时关闭连接并停止重连
+pub fn subscribe_class_updates(
+    path: String,
+    on_update: impl FnMut(ClassUpdate) + 'static,
+) -> ClassUpdateSubscription {
+    let subscription = ClassUpdateSubscription {
+        stopped: Rc::new(Cell::new(false)),
+        source: Rc::new(RefCell::new(None)),
+        wake: Rc::new(RefCell::new(None)),
+    };
+    let stopped = subscription.stopped.clone();
+    let current_source = subscription.source.clone();
+    let wake = subscription.wake.clone();
+    let on_update = Rc::new(RefCell::new(on_update));
+
+    spawn_local(async move {
+        let delay_ms = Rc::new(Cell::new(RECONNECT_INITIAL_DELAY_MS));
+        while !stopped.get() {
+            if let Ok(source) = web_sys::EventSource::new(&path) {
+                let on_open = {
+                    let on_update = on_update.clone();
+                    let delay_ms = delay_ms.clone();
+                    Closure::<dyn FnMut()>::new(move || {
+                        delay_ms.set(RECONNECT_INITIAL_DELAY_MS);
+                        (on_update.borrow_mut())(ClassUpdate::Connected);
+                    })
+                };
+                let on_message = {
+                    let on_update = on_update.clone();
+                    Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+                        move |event: web_sys::MessageEvent| {
+                            let data = event.data().as_string().unwrap_or_default();
+                            match serde_json::from_str::<ClassData>(&data) {
+                                Ok(data) => (on_update.borrow_mut())(ClassUpdate::Updated(data)),
+                                Err(e) => web_sys::console::error_1(
+                                    &format!("无法解析课程更新: {}", e).into(),
+                                ),
+                            }
+                        },
+                    )
+                };
+                source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+                source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+                // 等待连接出错，或取消订阅时被唤醒。
+                // 不使用 EventSource 自带的重连，由下面的退避控制重连间隔
+                let closed = js_sys::Promise::new(&mut |resolve, _| {
+                    source.set_onerror(Some(&resolve));
+                    *wake.borrow_mut() = Some(resolve);
+                });
+                *current_source.borrow_mut() = Some(source.clone());
+                let _ = wasm_bindgen_futures::JsFuture::from(closed).await;
+                source.close();
+                current_source.borrow_mut().take();
+                wake.borrow_mut().take();
+            }
+            if stopped.get() {
+                break;
+            }
+
+            let delay = delay_ms.get();
+            delay_ms.set((delay * 2).min(RECONNECT_MAX_DELAY_MS));
+            let promise = js_sys::Promise::new(&mut |resolve, _| {
+                web_sys::window()
+                    .unwrap()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, delay)
+                    .unwrap();
+            });
+            wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
+        }
+    });
+
+    subscription
+}
+
+pub struct ClassUpdateSubscription {
+    stopped: Rc<Cell<bool>>,
+    source: Rc<RefCell<Option<web_sys::EventSource>>>,
+    // 让等待中的重连循环立即结束
+    wake: Rc<RefCell<Option<js_sys::Function>>>,
+}
+
+impl Drop for ClassUpdateSubscription {
+    fn drop(&mut self) {
+        self.stopped.set(true);
+        if let Some(source) = self.source.borrow_mut().take() {
+            source.close();
+        }
+        if let Some(wake) = self.wake.borrow_mut().take() {
+            let _ = wake.call0(&JsValue::NULL);
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+// 服务器端：保存课程数据后广播，每个事件流只转发所订阅课程的更新
+#[cfg(feature = "server")]
+mod server {
+    use super::CLASS_EVENTS_PATH;
+    use crate::models::ClassData;
+    use crate::utils::api_error::ApiError;
+    use crate::utils::api::numeric_class_routes_enabled;
+    use crate::utils::auth::authorize_read;
+    use crate::utils::repository::with_class_repository;
+    use axum::extract::Path;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use std::sync::OnceLock;
+    use tokio::sync::broadcast;
+
+    // 广播通道的容量，订阅者落后超过该数量时跳过较早的更新
+    const CLASS_EVENTS_CAPACITY: usize = 64;
+
+    // 分享 slug 只用于选择转发给哪些订阅者，不会发送给客户端
+    #[derive(Clone)]
+    struct ClassEvent {
+        data: ClassData,
+        share_slug: Option<String>,
+    }
+
+    fn class_events() -> &'static broadcast::Sender<ClassEvent> {
+        static SENDER: OnceLock<broadcast::Sender<ClassEvent>> = OnceLock::new();
+        SENDER.get_or_init(|| broadcast::channel(CLASS_EVENTS_CAPACITY).0)
+    }
+
+    // 重新读取课程后广播。没有订阅者时不读取
+    pub async fn publish_class_change(site_id: String) {
+        if class_events().receiver_count() == 0 {
+            return;
+        }
+        let loaded = with_class_repository(move |repo| {
+            let Some(data) = repo.get_by_site_id(&site_id)? else {
+                return Ok(None);
+            };
+            let share_slug = repo.get_share_slug(&site_id)?;
+            Ok(Some(ClassEvent { data, share_slug }))
+        })
+        .await;
+        match loaded {
+            // 没有订阅者时发送失败，忽略即可
+            Ok(Some(event)) => {
+                let _ = class_events().send(event);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to load class for update event: {}", e),
+        }
+    }
+
+    fn class_event_stream(
+        matches: impl Fn(&ClassEvent) -> bool + Send + 'static,
+    ) -> Sse<impl futures_util::Stream<Item = Result<Event, axum::Error>>> {
+        let receiver = class_events().subscribe();
+        let stream = futures_util::stream::unfold(
+            (receiver, matches),
+            |(mut receiver, matches)| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(class_event) if matches(&class_event) => {
+                            let event = Event::default().json_data(&class_event.data);
+                            return Some((event, (receiver, matches)));
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            },
+        );
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    // 与 get_class_data_by_id 相同，关闭按 id 访问后不可用，读操作不公开时需要令牌
+    async fn events_by_id(Path(id): Path<i64>, headers: HeaderMap) -> Response {
+        if !numeric_class_routes_enabled() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        if let Err(e) = authorize_read(&headers).await {
+            let status = match e {
+                ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return (status, e.message()).into_response();
+        }
+        class_event_stream(move |event| event.data.id == id).into_response()
+    }
+
+    // 只转发分享 slug 仍为该值的更新，更换或停用分享链接后原订阅者收不到新的 slug
+    async fn events_by_slug(Path(slug): Path<String>) -> Response {
+        class_event_stream(move |event| event.share_slug.as_deref() == Some(slug.as_str()))
+            .into_response()
+    }
+
+    pub fn class_events_router() -> axum::Router {
+        axum::Router::new()
+            .route(&format!("{}/i/:id", CLASS_EVENTS_PATH), get(events_by_id))
+            .route(
+                &format!("{}/s/:slug", CLASS_EVENTS_PATH),
+                get(events_by_slug),
+            )
+    }
+}
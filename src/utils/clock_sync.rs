@@ -0,0 +1,96 @@
+use crate::utils::api::{get_current_time, get_time_zone};
+use crate::utils::api_error::{api_error_message, ApiError};
+use crate::utils::time::{set_app_time_zone, AppTimeZone, OffsetClock, SystemClock};
+use chrono::Duration;
+use dioxus::prelude::ServerFnError;
+use std::sync::atomic::{AtomicI64, Ordering};
+use wasm_bindgen_futures::spawn_local;
+
+// 本地时钟相对服务器时钟的偏移（毫秒），服务器时间 = 本地时间 + 偏移
+static CLOCK_OFFSET_MILLIS: AtomicI64 = AtomicI64::new(0);
+
+const CLOCK_OFFSET_STORAGE_KEY: &str = "clock_offset_ms";
+const TIME_ZONE_STORAGE_KEY: &str = "time_zone";
+// 重新校时的间隔（毫秒）
+const CLOCK_RESYNC_INTERVAL_MS: i32 = 10 * 60 * 1000;
+
+pub fn clock_offset() -> Duration {
+    Duration::milliseconds(CLOCK_OFFSET_MILLIS.load(Ordering::Relaxed))
+}
+
+// 客户端所有时间计算都应使用经过校正的时钟
+pub fn client_clock() -> OffsetClock<SystemClock> {
+    OffsetClock {
+        inner: SystemClock,
+        offset: clock_offset(),
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn cache_item(key: &str, value: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+fn cached_item(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+// 读取上次缓存的时区和偏移，使页面刷新后在校时完成前也能使用
+fn load_cached_clock_settings() {
+    if let Some(tz) = cached_item(TIME_ZONE_STORAGE_KEY).and_then(|v| v.parse::<AppTimeZone>().ok())
+    {
+        set_app_time_zone(tz);
+    }
+    if let Some(offset_millis) =
+        cached_item(CLOCK_OFFSET_STORAGE_KEY).and_then(|v| v.parse::<i64>().ok())
+    {
+        CLOCK_OFFSET_MILLIS.store(offset_millis, Ordering::Relaxed);
+    }
+}
+
+// 向服务器同步时区并校时一次，返回新的偏移
+pub async fn sync_clock() -> Result<Duration, ServerFnError<ApiError>> {
+    // 时区只用于页面显示时间，与服务器保持一致
+    let tz = get_time_zone().await?;
+    set_app_time_zone(tz);
+    cache_item(TIME_ZONE_STORAGE_KEY, &tz.to_string());
+
+    let sent_at = js_sys::Date::now();
+    let server_time = get_current_time().await?;
+    let received_at = js_sys::Date::now();
+
+    // 假设请求往返耗时对称，服务器时间对应往返的中点
+    let local_midpoint = sent_at + (received_at - sent_at) / 2.0;
+    let offset_millis = server_time.timestamp_millis() - local_midpoint as i64;
+    CLOCK_OFFSET_MILLIS.store(offset_millis, Ordering::Relaxed);
+    cache_item(CLOCK_OFFSET_STORAGE_KEY, &offset_millis.to_string());
+    Ok(Duration::milliseconds(offset_millis))
+}
+
+// 启动时校时，之后定期重新校时
+pub fn start_clock_sync() {
+    load_cached_clock_settings();
+    spawn_local(async move {
+        loop {
+            if let Err(e) = sync_clock().await {
+                web_sys::console::error_1(&format!("校时失败: {}", api_error_message(&e)).into());
+            }
+
+            let promise = js_sys::Promise::new(&mut |resolve, _| {
+                web_sys::window()
+                    .unwrap()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(
+                        &resolve,
+                        CLOCK_RESYNC_INTERVAL_MS,
+                    )
+                    .unwrap();
+            });
+            wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
+        }
+    });
+}
@@ -1,26 +1,125 @@
 #[cfg(feature = "server")]
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "server")]
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+#[cfg(feature = "server")]
+use crate::utils::migrations::{applied_migrations, run_migrations, MigrationMode};
 
 #[cfg(feature = "server")]
-static DB_POOL: OnceLock<Arc<Mutex<rusqlite::Connection>>> = OnceLock::new();
+static DB_POOL: OnceLock<Arc<DbPool>> = OnceLock::new();
 
 #[cfg(feature = "server")]
-pub fn get_db() -> Arc<Mutex<rusqlite::Connection>> {
+pub fn get_db() -> Arc<DbPool> {
     DB_POOL
-        .get_or_init(|| {
-            let conn = initialize_database().expect("Failed to initialize database");
-            Arc::new(Mutex::new(conn))
-        })
+        .get_or_init(|| Arc::new(DbPool::open().expect("Failed to initialize database")))
         .clone()
 }
 
 #[cfg(feature = "server")]
-pub fn initialize_database() -> Result<rusqlite::Connection, Box<dyn std::error::Error>> {
-    if !std::path::Path::new("lesson_data.db").exists() {
-        std::fs::File::create("lesson_data.db")?;
-        println!("Created new database file: lesson_data.db");
+const DB_PATH: &str = "lesson_data.db";
+
+// 只读连接数量的环境变量
+#[cfg(feature = "server")]
+const DB_READERS_ENV: &str = "DB_READERS";
+#[cfg(feature = "server")]
+const DEFAULT_DB_READERS: usize = 4;
+
+// 连接池：WAL 模式下多个只读连接可以并发读取，所有写操作共用一个写连接。
+// 连接上的操作都是阻塞的，只能在阻塞线程中调用（见 run_blocking）
+#[cfg(feature = "server")]
+pub struct DbPool {
+    writer: Mutex<rusqlite::Connection>,
+    readers: Vec<Mutex<rusqlite::Connection>>,
+    next_reader: AtomicUsize,
+}
+
+#[cfg(feature = "server")]
+impl DbPool {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let writer = initialize_database()?;
+        let reader_count = std::env::var(DB_READERS_ENV)
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|count| *count > 0)
+            .unwrap_or(DEFAULT_DB_READERS);
+        let readers = (0..reader_count)
+            .map(|_| {
+                let conn = rusqlite::Connection::open_with_flags(
+                    DB_PATH,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
+                        | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )?;
+                conn.busy_timeout(std::time::Duration::from_secs(5))?;
+                Ok(Mutex::new(conn))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        println!("Database pool opened with {} reader(s)", readers.len());
+        Ok(Self {
+            writer: Mutex::new(writer),
+            readers,
+            next_reader: AtomicUsize::new(0),
+        })
+    }
+
+    // 只有一个连接、读写共用的连接池，用于测试中的内存数据库
+    #[cfg(test)]
+    pub fn single(conn: rusqlite::Connection) -> Self {
+        Self {
+            writer: Mutex::new(conn),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
+        }
+    }
+
+    // 连接本身在 panic 后仍然可用，忽略锁中毒
+    fn lock(conn: &Mutex<rusqlite::Connection>) -> MutexGuard<'_, rusqlite::Connection> {
+        conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // 使用只读连接执行查询，优先选择空闲的连接；没有只读连接时使用写连接
+    pub fn read<T>(
+        &self,
+        f: impl FnOnce(&rusqlite::Connection) -> rusqlite::Result<T>,
+    ) -> rusqlite::Result<T> {
+        let count = self.readers.len();
+        if count == 0 {
+            return f(&Self::lock(&self.writer));
+        }
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        let idle = (0..count).find_map(|i| self.readers[(start + i) % count].try_lock().ok());
+        match idle {
+            Some(conn) => f(&conn),
+            None => f(&Self::lock(&self.readers[start % count])),
+        }
+    }
+
+    // 使用写连接执行操作
+    pub fn write<T>(
+        &self,
+        f: impl FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<T>,
+    ) -> rusqlite::Result<T> {
+        f(&mut Self::lock(&self.writer))
+    }
+}
+
+// 在 tokio 的阻塞线程池中执行数据库操作，避免阻塞异步运行时
+#[cfg(feature = "server")]
+pub async fn run_blocking<T, F>(f: F) -> Result<T, tokio::task::JoinError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await
+}
+
+#[cfg(feature = "server")]
+fn open_database() -> Result<rusqlite::Connection, Box<dyn std::error::Error>> {
+    if !std::path::Path::new(DB_PATH).exists() {
+        std::fs::File::create(DB_PATH)?;
+        println!("Created new database file: {}", DB_PATH);
     }
-    let conn = rusqlite::Connection::open("lesson_data.db")?;
+    let conn = rusqlite::Connection::open(DB_PATH)?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
     
     match conn.prepare("PRAGMA journal_mode=WAL").and_then(|mut stmt| {
         stmt.query_row([], |_| Ok(()))
@@ -36,93 +135,50 @@ pub fn initialize_database() -> Result<rusqlite::Connection, Box<dyn std::error:
         Ok(_) => println!("Synchronous mode set to NORMAL"),
         Err(e) => println!("Warning: Failed to set  mode: {}", e),
     }
+    Ok(conn)
+}
 
-    // Check if we need to migrate from old schema
-    let has_old_schema = conn
-        .prepare("PRAGMA table_info(class_data)")
-        .and_then(|mut stmt| {
-            let column_iter = stmt.query_map([], |row| {
-                let column_name: String = row.get(1)?;
-                Ok(column_name)
-            })?;
-            
-            let mut has_class_lesson_id = false;
-            let mut has_last_site_id = false;
-            
-            for column in column_iter {
-                match column {
-                    Ok(name) if name == "class_lesson_id" => has_class_lesson_id = true,
-                    Ok(name) if name == "last_site_id" => has_last_site_id = true,
-                    _ => {}
-                }
-            }
-            
-            Ok(has_class_lesson_id && has_last_site_id)
-        })
-        .unwrap_or(false);
-
-    if has_old_schema {
-        println!("Migrating database schema...");
-        
-        // Create new table with new schema
-        conn.execute(
-            "CREATE TABLE class_data_new (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                site_id TEXT NOT NULL UNIQUE,
-                class_name TEXT NOT NULL,
-                classes TEXT NOT NULL,
-                last_checkwork_id TEXT,
-                last_class_lesson_id TEXT,
-                last_created_time DATETIME,
-                is_expired BOOLEAN NOT NULL DEFAULT 0,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )",
-            []
-        )?;
-
-        // Migrate data: class_lesson_id -> site_id, last_site_id -> last_class_lesson_id
-        conn.execute(
-            "INSERT INTO class_data_new (site_id, class_name, classes, last_checkwork_id, last_class_lesson_id, last_created_time, is_expired, created_at, updated_at)
-             SELECT class_lesson_id, class_name, classes, last_checkwork_id, last_site_id, last_created_time, is_expired, created_at, updated_at
-             FROM class_data",
-            []
-        )?;
-
-        // Drop old table and rename new table
-        conn.execute("DROP TABLE class_data", [])?;
-        conn.execute("ALTER TABLE class_data_new RENAME TO class_data", [])?;
-
-        println!("Database schema migration completed successfully");
-    } else {
-        // Create the class_data table if it doesn't already exist
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS class_data (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                site_id TEXT NOT NULL UNIQUE,
-                class_name TEXT NOT NULL,
-                classes TEXT NOT NULL,
-                last_checkwork_id TEXT,
-                last_class_lesson_id TEXT,
-                last_created_time DATETIME,
-                is_expired BOOLEAN NOT NULL DEFAULT 0,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )",
-            []
-        )?;
+#[cfg(feature = "server")]
+pub fn initialize_database() -> Result<rusqlite::Connection, Box<dyn std::error::Error>> {
+    let mut conn = open_database()?;
+    let applied = run_migrations(&mut conn, MigrationMode::Apply)?;
+    if !applied.is_empty() {
+        println!("Applied {} database migration(s)", applied.len());
     }
 
-    // Create trigger
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS update_class_data_timestamp 
-         AFTER UPDATE ON class_data
-         BEGIN
-             UPDATE class_data SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
-         END",
-        []
-    )?;
-
     println!("Database initialized successfully");
     Ok(conn)
 }
+
+// 打印迁移记录以及将要执行的迁移，不修改数据库：
+// 以只读方式打开数据库文件，迁移在内存中的副本上执行后回滚
+#[cfg(feature = "server")]
+pub fn dry_run_migrations() -> Result<(), Box<dyn std::error::Error>> {
+    if !std::path::Path::new(DB_PATH).exists() {
+        return Err(format!("Database file not found: {}", DB_PATH).into());
+    }
+    let source = rusqlite::Connection::open_with_flags(
+        DB_PATH,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    source.busy_timeout(std::time::Duration::from_secs(5))?;
+    for migration in applied_migrations(&source)? {
+        println!(
+            "Migration #{} {} applied at {}",
+            migration.version, migration.name, migration.applied_at
+        );
+    }
+
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    rusqlite::backup::Backup::new(&source, &mut conn)?.run_to_completion(
+        1024,
+        std::time::Duration::ZERO,
+        None,
+    )?;
+    conn.execute("PRAGMA foreign_keys=ON", [])?;
+    let pending = run_migrations(&mut conn, MigrationMode::DryRun)?;
+    if pending.is_empty() {
+        println!("Database schema is up to date");
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::utils::class_events::publish_class_change;
+use crate::utils::repository::with_class_repository;
+use crate::utils::signing_code::SIGNING_CODE_TTL_MINUTES;
+use crate::utils::time::CheckTime;
+use chrono::Duration;
+
+// 最新签到码生成超过多少分钟后将课程标记为过期的环境变量，默认与签到码有效期相同
+const CLASS_EXPIRY_MINUTES_ENV: &str = "CLASS_EXPIRY_MINUTES";
+// 检查间隔（秒）
+const EXPIRY_CHECK_INTERVAL_SECS: u64 = 60;
+
+fn class_expiry_minutes() -> i64 {
+    std::env::var(CLASS_EXPIRY_MINUTES_ENV)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(SIGNING_CODE_TTL_MINUTES)
+}
+
+// 定期将最新签到码已超过阈值的课程标记为过期，并推送给正在显示这些课程的页面。
+// 过期标记在课程收到新的扫码时清除（见 ClassRepository::upsert_scan）
+pub fn spawn_expiry_task() {
+    let threshold = Duration::minutes(class_expiry_minutes());
+    println!(
+        "Class expiry task started (threshold {} minutes)",
+        threshold.num_minutes()
+    );
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(EXPIRY_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let before = CheckTime::now() - threshold;
+            match with_class_repository(move |repo| repo.expire_stale(before)).await {
+                Ok(expired) if expired.is_empty() => {}
+                Ok(expired) => {
+                    println!("Marked {} class(es) as expired", expired.len());
+                    for site_id in expired {
+                        publish_class_change(site_id).await;
+                    }
+                }
+                Err(e) => eprintln!("Class expiry check failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,647 @@
+use crate::utils::time::{CheckTime, WIRE_OFFSET_SECS};
+use rusqlite::Connection;
+
+// 单个数据库迁移，按 version 递增顺序执行
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+// 所有迁移，新增迁移只能追加到末尾
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "class_data_keyed_by_site_id",
+        up: migrate_class_data_keyed_by_site_id,
+    },
+    Migration {
+        version: 2,
+        name: "scan_logs",
+        up: migrate_scan_logs,
+    },
+    Migration {
+        version: 3,
+        name: "scan_history",
+        up: migrate_scan_history,
+    },
+    Migration {
+        version: 4,
+        name: "courses_and_lessons",
+        up: migrate_courses_and_lessons,
+    },
+    Migration {
+        version: 5,
+        name: "share_slugs",
+        up: migrate_share_slugs,
+    },
+    Migration {
+        version: 6,
+        name: "access_tokens",
+        up: migrate_access_tokens,
+    },
+    Migration {
+        version: 7,
+        name: "normalize_legacy_timestamps",
+        up: migrate_normalize_legacy_timestamps,
+    },
+    Migration {
+        version: 8,
+        name: "unique_scans_with_nulls",
+        up: migrate_unique_scans_with_nulls,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationMode {
+    Apply,
+    // 在事务中执行所有待执行的迁移后回滚，只用于检查
+    DryRun,
+}
+
+#[derive(Debug, Clone)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: CheckTime,
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+fn ensure_migration_log(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn apply_migration(conn: &Connection, migration: &Migration) -> rusqlite::Result<AppliedMigration> {
+    (migration.up)(conn)?;
+    let applied_at = CheckTime::now();
+    conn.execute(
+        "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![migration.version, migration.name, applied_at],
+    )?;
+    conn.pragma_update(None, "user_version", migration.version)?;
+    Ok(AppliedMigration {
+        version: migration.version,
+        name: migration.name.to_string(),
+        applied_at,
+    })
+}
+
+// 执行所有版本号大于当前 user_version 的迁移，返回本次执行（或将要执行）的迁移
+pub fn run_migrations(
+    conn: &mut Connection,
+    mode: MigrationMode,
+) -> rusqlite::Result<Vec<AppliedMigration>> {
+    let current_version = schema_version(conn)?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current_version);
+    let mut applied = Vec::new();
+
+    match mode {
+        MigrationMode::Apply => {
+            // 每个迁移在独立的事务中执行，失败时只回滚当前迁移
+            for migration in pending {
+                let tx = conn.transaction()?;
+                ensure_migration_log(&tx)?;
+                applied.push(apply_migration(&tx, migration)?);
+                tx.commit()?;
+                println!(
+                    "Applied migration #{} {}",
+                    migration.version, migration.name
+                );
+            }
+        }
+        MigrationMode::DryRun => {
+            let tx = conn.transaction()?;
+            ensure_migration_log(&tx)?;
+            for migration in pending {
+                applied.push(apply_migration(&tx, migration)?);
+                println!(
+                    "[dry-run] Migration #{} {} would be applied",
+                    migration.version, migration.name
+                );
+            }
+            tx.rollback()?;
+        }
+    }
+
+    Ok(applied)
+}
+
+// 读取已执行的迁移记录。只读取，不会创建迁移记录表，可以在只读连接上调用
+pub fn applied_migrations(conn: &Connection) -> rusqlite::Result<Vec<AppliedMigration>> {
+    let has_migration_log: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_migration_log {
+        return Ok(Vec::new());
+    }
+    let mut stmt =
+        conn.prepare("SELECT version, name, applied_at FROM schema_migrations ORDER BY version")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            applied_at: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+// #1：class_data 以 site_id 为唯一键。
+// 旧版本把课程 id 存在 class_lesson_id、把课次 id 存在 last_site_id，需要改名迁移
+fn migrate_class_data_keyed_by_site_id(conn: &Connection) -> rusqlite::Result<()> {
+    let has_old_schema = {
+        let mut stmt = conn.prepare("PRAGMA table_info(class_data)")?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        columns.iter().any(|name| name == "class_lesson_id")
+            && columns.iter().any(|name| name == "last_site_id")
+    };
+
+    let create_table = |name: &str| {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    site_id TEXT NOT NULL UNIQUE,
+                    class_name TEXT NOT NULL,
+                    classes TEXT NOT NULL,
+                    last_checkwork_id TEXT,
+                    last_class_lesson_id TEXT,
+                    last_created_time DATETIME,
+                    is_expired BOOLEAN NOT NULL DEFAULT 0,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )",
+                name
+            ),
+            [],
+        )
+    };
+
+    if has_old_schema {
+        println!("Migrating legacy class_data schema...");
+        create_table("class_data_new")?;
+
+        // class_lesson_id -> site_id, last_site_id -> last_class_lesson_id
+        conn.execute(
+            "INSERT INTO class_data_new (site_id, class_name, classes, last_checkwork_id, last_class_lesson_id, last_created_time, is_expired, created_at, updated_at)
+             SELECT class_lesson_id, class_name, classes, last_checkwork_id, last_site_id, last_created_time, is_expired, created_at, updated_at
+             FROM class_data",
+            [],
+        )?;
+        conn.execute("DROP TABLE class_data", [])?;
+        conn.execute("ALTER TABLE class_data_new RENAME TO class_data", [])?;
+    } else {
+        create_table("class_data")?;
+    }
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_class_data_timestamp
+         AFTER UPDATE ON class_data
+         BEGIN
+             UPDATE class_data SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+         END",
+        [],
+    )?;
+    Ok(())
+}
+
+// #2：扫码日志从 scanlogs.txt 改为存入数据库
+fn migrate_scan_logs(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS scan_logs (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             logged_at TEXT NOT NULL,
+             raw TEXT NOT NULL,
+             site_id TEXT,
+             verdict_kind TEXT NOT NULL,
+             verdict TEXT NOT NULL,
+             source TEXT NOT NULL,
+             user_agent TEXT
+         );
+         CREATE INDEX IF NOT EXISTS idx_scan_logs_logged_at ON scan_logs(logged_at);
+         CREATE INDEX IF NOT EXISTS idx_scan_logs_site_id ON scan_logs(site_id);",
+    )
+}
+
+// #3：每次扫码保存为 scans 表中的一行，class_data 的 last_* 列改为从最新一行得出。
+// 原有的 last_* 数据作为每个课程的第一条记录迁入。
+// 不使用 ALTER TABLE ... DROP COLUMN（需要 SQLite 3.35+），而是重建 class_data：
+// scans 先引用新表，删除旧表时不会触发 ON DELETE CASCADE，新表改名后引用随之更新
+fn migrate_scan_history(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE class_data_new (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             site_id TEXT NOT NULL UNIQUE,
+             class_name TEXT NOT NULL,
+             classes TEXT NOT NULL,
+             is_expired BOOLEAN NOT NULL DEFAULT 0,
+             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+         );
+         INSERT INTO class_data_new (id, site_id, class_name, classes, is_expired, created_at, updated_at)
+         SELECT id, site_id, class_name, classes, is_expired, created_at, updated_at
+         FROM class_data;
+
+         CREATE TABLE IF NOT EXISTS scans (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             class_id INTEGER NOT NULL REFERENCES class_data_new(id) ON DELETE CASCADE,
+             checkwork_id TEXT,
+             class_lesson_id TEXT,
+             created_time TEXT,
+             scanned_at TEXT NOT NULL,
+             UNIQUE (class_id, checkwork_id, class_lesson_id, created_time)
+         );
+         CREATE INDEX IF NOT EXISTS idx_scans_class_id ON scans(class_id);
+
+         INSERT INTO scans (class_id, checkwork_id, class_lesson_id, created_time, scanned_at)
+         SELECT id, last_checkwork_id, last_class_lesson_id, last_created_time,
+                COALESCE(last_created_time, updated_at, created_at)
+         FROM class_data
+         WHERE last_checkwork_id IS NOT NULL
+            OR last_class_lesson_id IS NOT NULL
+            OR last_created_time IS NOT NULL;
+
+         DROP TABLE class_data;
+         ALTER TABLE class_data_new RENAME TO class_data;",
+    )
+}
+
+// #4：class_data 改名为 courses，新增课次表 lessons 及其签到 id 表 lesson_checkworks，
+// 并从已有的扫码记录生成课次。
+// 原来的触发器会把 updated_at 改写成 UTC 时间，与应用写入的时间格式不一致，改为只由应用维护
+fn migrate_courses_and_lessons(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS update_class_data_timestamp;
+         ALTER TABLE class_data RENAME TO courses;
+
+         CREATE TABLE IF NOT EXISTS lessons (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             course_id INTEGER NOT NULL REFERENCES courses(id) ON DELETE CASCADE,
+             class_lesson_id TEXT NOT NULL,
+             first_seen TEXT NOT NULL,
+             last_seen TEXT NOT NULL,
+             UNIQUE (course_id, class_lesson_id)
+         );
+         CREATE TABLE IF NOT EXISTS lesson_checkworks (
+             lesson_id INTEGER NOT NULL REFERENCES lessons(id) ON DELETE CASCADE,
+             checkwork_id TEXT NOT NULL,
+             first_seen TEXT NOT NULL,
+             PRIMARY KEY (lesson_id, checkwork_id)
+         );
+
+         INSERT INTO lessons (course_id, class_lesson_id, first_seen, last_seen)
+         SELECT class_id, class_lesson_id, MIN(scanned_at), MAX(scanned_at)
+         FROM scans
+         WHERE class_lesson_id IS NOT NULL
+         GROUP BY class_id, class_lesson_id;
+
+         INSERT INTO lesson_checkworks (lesson_id, checkwork_id, first_seen)
+         SELECT l.id, s.checkwork_id, MIN(s.scanned_at)
+         FROM scans s
+         JOIN lessons l ON l.course_id = s.class_id AND l.class_lesson_id = s.class_lesson_id
+         WHERE s.checkwork_id IS NOT NULL
+         GROUP BY l.id, s.checkwork_id;",
+    )
+}
+
+// #5：课程的分享 slug。已有课程生成与 repository::new_share_slug 相同格式的随机值，
+// 为 NULL 表示分享链接已停用
+fn migrate_share_slugs(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE courses ADD COLUMN share_slug TEXT;
+         UPDATE courses SET share_slug = lower(hex(randomblob(12)));
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_courses_share_slug ON courses(share_slug);",
+    )
+}
+
+// #6：写操作使用的访问令牌，只保存令牌的 SHA-256，revoked_at 不为 NULL 表示已吊销
+fn migrate_access_tokens(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS access_tokens (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             name TEXT NOT NULL,
+             token_hash TEXT NOT NULL UNIQUE,
+             created_at TEXT NOT NULL,
+             revoked_at TEXT
+         );",
+    )
+}
+
+// #7：旧版本由 CURRENT_TIMESTAMP 和触发器写入的时间是 UTC 的 "YYYY-MM-DD HH:MM:SS"，
+// 与应用写入的 CHECK_TIME_FORMAT（+08:00 墙上时间）混在一起时无法按字符串比较和排序。
+// 统一改写为 CHECK_TIME_FORMAT，包括 #3、#4 从这些列复制出的扫码和课次时间
+fn migrate_normalize_legacy_timestamps(conn: &Connection) -> rusqlite::Result<()> {
+    const LEGACY_COLUMNS: &[(&str, &str)] = &[
+        ("courses", "created_at"),
+        ("courses", "updated_at"),
+        ("scans", "scanned_at"),
+        ("lessons", "first_seen"),
+        ("lessons", "last_seen"),
+        ("lesson_checkworks", "first_seen"),
+    ];
+    for (table, column) in LEGACY_COLUMNS {
+        conn.execute(
+            &format!(
+                "UPDATE {table} SET {column} = strftime('%Y-%m-%dT%H:%M:%f', {column}, ?1)
+                 WHERE {column} GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9] [0-9][0-9]:[0-9][0-9]:[0-9][0-9]'",
+            ),
+            rusqlite::params![format!("+{} seconds", WIRE_OFFSET_SECS)],
+        )?;
+    }
+    Ok(())
+}
+
+// #8：scans 表的 UNIQUE 约束把 NULL 视为互不相同，缺少课次 id 等字段的同一个签到码会被重复记录，
+// 而内存实现把它们视为重复。删除已有的重复记录（保留最早的一条），再建立把 NULL 视为相同的唯一索引
+fn migrate_unique_scans_with_nulls(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "DELETE FROM scans WHERE id NOT IN (
+             SELECT MIN(id) FROM scans
+             GROUP BY class_id, checkwork_id, class_lesson_id, created_time
+         );
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_scans_unique_scan ON scans (
+             class_id,
+             IFNULL(checkwork_id, ''),
+             IFNULL(class_lesson_id, ''),
+             IFNULL(created_time, '')
+         );",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_names(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn reading_migration_log_does_not_create_it() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(applied_migrations(&conn).unwrap().is_empty());
+        assert!(table_names(&conn).is_empty());
+    }
+
+    // 最早版本的 class_data：课程 id 在 class_lesson_id，课次 id 在 last_site_id
+    fn legacy_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("PRAGMA foreign_keys=ON", []).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE class_data (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 class_lesson_id TEXT NOT NULL UNIQUE,
+                 class_name TEXT NOT NULL,
+                 classes TEXT NOT NULL,
+                 last_checkwork_id TEXT,
+                 last_site_id TEXT,
+                 last_created_time DATETIME,
+                 is_expired BOOLEAN NOT NULL DEFAULT 0,
+                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                 updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+             );
+             INSERT INTO class_data (class_lesson_id, class_name, classes, last_checkwork_id,
+                                     last_site_id, last_created_time, created_at, updated_at)
+             VALUES ('site-1', '数学', '一班', 'C1', 'L1', '2024-03-01T08:00:00.000',
+                     '2024-03-01 00:00:00', '2024-03-01 00:05:00');
+             INSERT INTO class_data (class_lesson_id, class_name, classes)
+             VALUES ('site-2', '语文', '二班');
+             INSERT INTO class_data (class_lesson_id, class_name, classes, last_site_id,
+                                     created_at, updated_at)
+             VALUES ('site-3', '英语', '三班', 'L9', '2024-03-01 23:00:00', '2024-03-02 01:00:00');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn column_names(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(1)).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn upgrades_legacy_database_without_losing_scans() {
+        let mut conn = legacy_database();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+
+        assert_eq!(
+            column_names(&conn, "courses"),
+            [
+                "id",
+                "site_id",
+                "class_name",
+                "classes",
+                "is_expired",
+                "created_at",
+                "updated_at",
+                "share_slug"
+            ]
+        );
+        let scans: Vec<(String, Option<String>, Option<String>)> = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT c.site_id, s.checkwork_id, s.class_lesson_id
+                     FROM scans s JOIN courses c ON c.id = s.class_id ORDER BY c.site_id",
+                )
+                .unwrap();
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .unwrap();
+            rows.collect::<rusqlite::Result<_>>().unwrap()
+        };
+        assert_eq!(
+            scans,
+            [
+                (
+                    "site-1".to_string(),
+                    Some("C1".to_string()),
+                    Some("L1".to_string())
+                ),
+                ("site-3".to_string(), None, Some("L9".to_string()))
+            ]
+        );
+        let lessons: i64 = conn
+            .query_row("SELECT COUNT(*) FROM lessons", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(lessons, 2);
+
+        // scans 的外键随表改名指向 courses
+        let scans_sql: String = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE name = 'scans'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(scans_sql.contains("REFERENCES \"courses\"(id)"));
+        let violations = conn
+            .prepare("PRAGMA foreign_key_check")
+            .unwrap()
+            .query_map([], |_| Ok(()))
+            .unwrap()
+            .count();
+        assert_eq!(violations, 0);
+        conn.execute("DELETE FROM courses WHERE site_id = 'site-1'", [])
+            .unwrap();
+        let scans: i64 = conn
+            .query_row("SELECT COUNT(*) FROM scans", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(scans, 1);
+    }
+
+    #[test]
+    fn normalizes_legacy_utc_timestamps() {
+        let mut conn = legacy_database();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+
+        let course_times = |site_id: &str| -> (String, String) {
+            conn.query_row(
+                "SELECT created_at, updated_at FROM courses WHERE site_id = ?1",
+                [site_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            course_times("site-1"),
+            (
+                "2024-03-01T08:00:00.000".to_string(),
+                "2024-03-01T08:05:00.000".to_string()
+            )
+        );
+        // UTC 23:00 是北京时间第二天 07:00
+        assert_eq!(
+            course_times("site-3"),
+            (
+                "2024-03-02T07:00:00.000".to_string(),
+                "2024-03-02T09:00:00.000".to_string()
+            )
+        );
+        let (created_at, updated_at) = course_times("site-2");
+        created_at.parse::<CheckTime>().unwrap();
+        assert!(created_at.contains('T') && updated_at.contains('T'));
+
+        // 已经是 CHECK_TIME_FORMAT 的签到码时间保持不变，从 updated_at 复制的时间被改写
+        let scanned_at = |site_id: &str| -> String {
+            conn.query_row(
+                "SELECT s.scanned_at FROM scans s JOIN courses c ON c.id = s.class_id
+                 WHERE c.site_id = ?1",
+                [site_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(scanned_at("site-1"), "2024-03-01T08:00:00.000");
+        assert_eq!(scanned_at("site-3"), "2024-03-02T09:00:00.000");
+        let lesson: (String, String) = conn
+            .query_row(
+                "SELECT first_seen, last_seen FROM lessons WHERE class_lesson_id = 'L9'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            lesson,
+            (
+                "2024-03-02T09:00:00.000".to_string(),
+                "2024-03-02T09:00:00.000".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn removes_duplicate_scans_with_missing_fields() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+        // 回到 #8 之前的状态：只有把 NULL 视为互不相同的 UNIQUE 约束
+        conn.execute_batch(
+            "DROP INDEX idx_scans_unique_scan;
+             INSERT INTO courses (site_id, class_name, classes, is_expired, created_at, updated_at)
+             VALUES ('site-1', '数学', '一班', 0, '2024-03-01T08:00:00.000', '2024-03-01T08:00:00.000');
+             INSERT INTO scans (class_id, checkwork_id, class_lesson_id, created_time, scanned_at)
+             VALUES (1, 'C1', NULL, '2024-03-01T08:00:00.000', '2024-03-01T08:00:00.000'),
+                    (1, 'C1', NULL, '2024-03-01T08:00:00.000', '2024-03-01T08:01:00.000'),
+                    (1, 'C1', 'L1', '2024-03-01T08:00:00.000', '2024-03-01T08:02:00.000');",
+        )
+        .unwrap();
+
+        migrate_unique_scans_with_nulls(&conn).unwrap();
+        let scanned_at: Vec<String> = {
+            let mut stmt = conn
+                .prepare("SELECT scanned_at FROM scans ORDER BY id")
+                .unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<rusqlite::Result<_>>().unwrap()
+        };
+        assert_eq!(
+            scanned_at,
+            ["2024-03-01T08:00:00.000", "2024-03-01T08:02:00.000"]
+        );
+        let inserted = conn
+            .execute(
+                "INSERT INTO scans (class_id, checkwork_id, class_lesson_id, created_time, scanned_at)
+                 VALUES (1, 'C1', NULL, '2024-03-01T08:00:00.000', '2024-03-01T08:03:00.000')
+                 ON CONFLICT DO NOTHING",
+                [],
+            )
+            .unwrap();
+        assert_eq!(inserted, 0);
+    }
+
+    #[test]
+    fn dry_run_rolls_back_all_migrations() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let pending = run_migrations(&mut conn, MigrationMode::DryRun).unwrap();
+        assert_eq!(pending.len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        assert!(table_names(&conn).is_empty());
+    }
+
+    #[test]
+    fn apply_records_each_migration_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let applied = run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(
+            schema_version(&conn).unwrap(),
+            MIGRATIONS.last().unwrap().version
+        );
+        assert!(run_migrations(&mut conn, MigrationMode::Apply)
+            .unwrap()
+            .is_empty());
+        let versions: Vec<i64> = applied_migrations(&conn)
+            .unwrap()
+            .iter()
+            .map(|migration| migration.version)
+            .collect();
+        let expected: Vec<i64> = MIGRATIONS
+            .iter()
+            .map(|migration| migration.version)
+            .collect();
+        assert_eq!(versions, expected);
+    }
+}
@@ -1,9 +1,28 @@
 pub mod db;
+#[cfg(feature = "server")]
+pub mod migrations;
 pub mod signing_code;
 pub mod time;
 pub mod api;
+pub mod api_error;
+#[cfg(feature = "server")]
+pub mod auth;
+pub mod class_events;
+pub mod clock_sync;
+pub mod qr;
+#[cfg(feature = "server")]
+pub mod repository;
+#[cfg(feature = "server")]
+pub mod scan_logs;
+#[cfg(feature = "server")]
+pub mod expiry;
+#[cfg(feature = "server")]
+pub mod rate_limit;
 
-pub use db::*;
 pub use signing_code::*;
 pub use time::*;
-pub use api::*;
\ No newline at end of file
+pub use api::*;
+pub use api_error::*;
+pub use class_events::*;
+pub use clock_sync::*;
+pub use qr::*;
\ No newline at end of file
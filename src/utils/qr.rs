@@ -0,0 +1,85 @@
+use qrcode::{Color, QrCode};
+
+pub use qrcode::types::QrError;
+pub use qrcode::EcLevel;
+
+// 二维码渲染参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QrOptions {
+    // 输出图片的边长（像素）
+    pub size: u32,
+    // 四周留白的宽度（以模块为单位）
+    pub margin: u32,
+    // 纠错等级
+    pub ec_level: EcLevel,
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            margin: 4,
+            ec_level: EcLevel::M,
+        }
+    }
+}
+
+// 将内容编码为内联 SVG 字符串
+pub fn render_qr_svg(data: &str, options: &QrOptions) -> Result<String, QrError> {
+    let code = QrCode::with_error_correction_level(data.as_bytes(), options.ec_level)?;
+    let width = code.width();
+    let margin = options.margin as usize;
+    let total = width + margin * 2;
+    let colors = code.to_colors();
+
+    // 将每一行连续的深色模块合并成一个矩形，减小 SVG 体积
+    let mut path = String::new();
+    for y in 0..width {
+        let row = &colors[y * width..(y + 1) * width];
+        let mut x = 0;
+        while x < width {
+            if row[x] == Color::Dark {
+                let start = x;
+                while x < width && row[x] == Color::Dark {
+                    x += 1;
+                }
+                path.push_str(&format!(
+                    "M{},{}h{}v1h-{}z",
+                    start + margin,
+                    y + margin,
+                    x - start,
+                    x - start
+                ));
+            } else {
+                x += 1;
+            }
+        }
+    }
+
+    Ok(format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" "#,
+            r#"width="{size}" height="{size}" viewBox="0 0 {total} {total}" "#,
+            r#"shape-rendering="crispEdges">"#,
+            r##"<rect width="100%" height="100%" fill="#ffffff"/>"##,
+            r##"<path fill="#000000" d="{path}"/>"##,
+            "</svg>"
+        ),
+        size = options.size,
+        total = total,
+        path = path
+    ))
+}
+
+// 将内容编码为可直接用于 img src 的 data URL
+pub fn render_qr_data_url(data: &str, options: &QrOptions) -> Result<String, QrError> {
+    let svg = render_qr_svg(data, options)?;
+    // 仅转义 data URL 中有特殊含义的字符
+    let encoded = svg
+        .replace('%', "%25")
+        .replace('#', "%23")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+        .replace('"', "'");
+    Ok(format!("data:image/svg+xml;charset=utf-8,{}", encoded))
+}
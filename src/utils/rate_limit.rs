@@ -0,0 +1,466 @@
+use crate::models::RateLimitStats;
+use crate::utils::api_error::ApiError;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use dioxus::prelude::server_fn::error::ServerFnErrorSerde;
+use dioxus::prelude::ServerFnError;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+// 按客户端 IP 和接口限制 /api/ 下的请求频率（令牌桶）。
+// 每个接口的限制为 “容量/秒数”：最多连续请求“容量”次，之后每隔“秒数/容量”秒恢复一次
+
+// 覆盖默认限制的环境变量，例如 "save_signing_code=10/60,default=300/60"；设为 "off" 时不限制
+const RATE_LIMITS_ENV: &str = "RATE_LIMITS";
+
+// 未单独配置的接口使用 default 的限制
+const DEFAULT_RATE_LIMITS: &[(&str, RateLimit)] = &[
+    ("default", RateLimit::new(120, 60)),
+    // 每次调用都会写入数据库
+    ("save_signing_code", RateLimit::new(20, 60)),
+    ("log_scan_result", RateLimit::new(30, 60)),
+    ("save_scanned_code_data", RateLimit::new(30, 60)),
+    // 防止逐个尝试令牌
+    ("sign_in", RateLimit::new(5, 60)),
+];
+
+const API_PATH_PREFIX: &str = "/api/";
+
+// 记录的令牌桶超过该数量时，清理已经恢复满的桶。清理后剩余的桶数翻倍之前不再清理，
+// 每次清理遍历的桶至少有一半是上次清理后新增的，均摊到每个请求上的开销是常数
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+// 统计的接口数上限，之后出现的新路径计入 OTHER_ENDPOINTS，避免随意请求的路径占满内存
+const MAX_COUNTED_ENDPOINTS: usize = 256;
+const OTHER_ENDPOINTS: &str = "other";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RateLimit {
+    capacity: u32,
+    per_secs: u32,
+}
+
+impl RateLimit {
+    const fn new(capacity: u32, per_secs: u32) -> Self {
+        Self { capacity, per_secs }
+    }
+
+    // 每秒恢复的次数
+    fn refill_rate(&self) -> f64 {
+        self.capacity as f64 / self.per_secs as f64
+    }
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (capacity, per_secs) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid rate limit (expected capacity/seconds): {}", s))?;
+        let capacity = capacity.trim().parse::<u32>().ok().filter(|n| *n > 0);
+        let per_secs = per_secs.trim().parse::<u32>().ok().filter(|n| *n > 0);
+        match (capacity, per_secs) {
+            (Some(capacity), Some(per_secs)) => Ok(RateLimit::new(capacity, per_secs)),
+            _ => Err(format!("invalid rate limit: {}", s)),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Buckets {
+    entries: HashMap<(IpAddr, String), Bucket>,
+    // 桶数超过该值时清理一次
+    sweep_above: usize,
+}
+
+impl Default for Buckets {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            sweep_above: MAX_TRACKED_BUCKETS,
+        }
+    }
+}
+
+#[derive(Default)]
+struct EndpointCounters {
+    allowed: u64,
+    rejected: u64,
+}
+
+struct RateLimiter {
+    default_limit: RateLimit,
+    limits: HashMap<String, RateLimit>,
+    buckets: Mutex<Buckets>,
+    counters: Mutex<HashMap<String, EndpointCounters>>,
+}
+
+impl RateLimiter {
+    // 读取默认限制和环境变量中的配置，设为 "off" 时返回 None
+    fn from_env() -> Option<Self> {
+        Self::from_config(std::env::var(RATE_LIMITS_ENV).ok().as_deref())
+    }
+
+    // 用 RATE_LIMITS 格式的配置覆盖默认限制，无法解析的条目会被忽略
+    fn from_config(config: Option<&str>) -> Option<Self> {
+        let mut limits: HashMap<String, RateLimit> = DEFAULT_RATE_LIMITS
+            .iter()
+            .map(|(endpoint, limit)| (endpoint.to_string(), *limit))
+            .collect();
+        match config {
+            Some(value) if value.trim() == "off" => return None,
+            Some(value) => {
+                for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+                    let parsed = entry
+                        .split_once('=')
+                        .ok_or_else(|| format!("invalid entry: {}", entry))
+                        .and_then(|(endpoint, limit)| {
+                            Ok((endpoint.trim().to_string(), limit.parse::<RateLimit>()?))
+                        });
+                    match parsed {
+                        Ok((endpoint, limit)) => {
+                            limits.insert(endpoint, limit);
+                        }
+                        Err(e) => eprintln!("Ignoring {} entry: {}", RATE_LIMITS_ENV, e),
+                    }
+                }
+            }
+            None => {}
+        }
+        let default_limit = limits.remove("default")?;
+        Some(Self {
+            default_limit,
+            limits,
+            buckets: Mutex::new(Buckets::default()),
+            counters: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn limit_for(&self, endpoint: &str) -> RateLimit {
+        self.limits
+            .get(endpoint)
+            .copied()
+            .unwrap_or(self.default_limit)
+    }
+
+    // 删除已经恢复满的桶，这些客户端下次请求时会重新得到一个满的桶
+    fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+        buckets.entries.retain(|(_, endpoint), bucket| {
+            let limit = self.limit_for(endpoint);
+            let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+            bucket.tokens + elapsed * limit.refill_rate() < limit.capacity as f64
+        });
+        buckets.sweep_above = (buckets.entries.len() * 2).max(MAX_TRACKED_BUCKETS);
+    }
+
+    // 取走一次请求的令牌。不足时返回需要等待的秒数
+    fn acquire(&self, ip: IpAddr, endpoint: &str) -> Result<(), u64> {
+        self.acquire_at(ip, endpoint, Instant::now())
+    }
+
+    fn acquire_at(&self, ip: IpAddr, endpoint: &str, now: Instant) -> Result<(), u64> {
+        let limit = self.limit_for(endpoint);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.entries.len() > buckets.sweep_above {
+            self.sweep(&mut buckets, now);
+        }
+        let bucket = buckets
+            .entries
+            .entry((ip, endpoint.to_string()))
+            .or_insert_with(|| Bucket {
+                tokens: limit.capacity as f64,
+                updated_at: now,
+            });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.refill_rate()).min(limit.capacity as f64);
+        bucket.updated_at = now;
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / limit.refill_rate())
+                .ceil()
+                .max(1.0) as u64)
+        };
+        drop(buckets);
+
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let key = if counters.contains_key(endpoint) || counters.len() < MAX_COUNTED_ENDPOINTS {
+            endpoint
+        } else {
+            OTHER_ENDPOINTS
+        };
+        let counter = counters.entry(key.to_string()).or_default();
+        match result {
+            Ok(()) => counter.allowed += 1,
+            Err(_) => counter.rejected += 1,
+        }
+        result
+    }
+}
+
+fn rate_limiter() -> Option<&'static RateLimiter> {
+    static RATE_LIMITER: OnceLock<Option<RateLimiter>> = OnceLock::new();
+    RATE_LIMITER.get_or_init(RateLimiter::from_env).as_ref()
+}
+
+// 服务在本机的反向代理之后时，对端地址都是回环地址，
+// 此时使用代理追加在 X-Forwarded-For 末尾的地址作为客户端 IP
+fn client_ip(request: &Request) -> Option<IpAddr> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())?;
+    if !peer.is_loopback() {
+        return Some(peer);
+    }
+    let forwarded = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+    Some(forwarded.unwrap_or(peer))
+}
+
+// 超过限制时返回 429，响应体是服务端函数的错误格式，客户端会得到 ApiError::RateLimited
+fn rate_limited_response(retry_after_secs: u64) -> Response {
+    let body = ServerFnError::WrappedServerError(ApiError::RateLimited { retry_after_secs })
+        .ser()
+        .unwrap_or_default();
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, body).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+    response
+}
+
+// axum 中间件，只限制 /api/ 下的请求，页面和静态文件不受影响
+pub async fn rate_limit(request: Request, next: Next) -> Response {
+    let endpoint = request
+        .uri()
+        .path()
+        .strip_prefix(API_PATH_PREFIX)
+        .and_then(|rest| rest.split('/').next())
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(|endpoint| endpoint.to_string());
+    if let (Some(limiter), Some(endpoint), Some(ip)) =
+        (rate_limiter(), endpoint, client_ip(&request))
+    {
+        if let Err(retry_after_secs) = limiter.acquire(ip, &endpoint) {
+            return rate_limited_response(retry_after_secs);
+        }
+    }
+    next.run(request).await
+}
+
+// 各接口自启动以来放行和拒绝的请求数，按接口名排序
+pub fn rate_limit_stats() -> Vec<RateLimitStats> {
+    let Some(limiter) = rate_limiter() else {
+        return Vec::new();
+    };
+    let counters = limiter.counters.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stats: Vec<RateLimitStats> = counters
+        .iter()
+        .map(|(endpoint, counter)| RateLimitStats {
+            endpoint: endpoint.clone(),
+            allowed: counter.allowed,
+            rejected: counter.rejected,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use std::time::Duration;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([203, 0, 113, last])
+    }
+
+    fn limiter(config: &str) -> RateLimiter {
+        RateLimiter::from_config(Some(config)).unwrap()
+    }
+
+    #[test]
+    fn allows_a_burst_up_to_capacity() {
+        let limiter = limiter("default=3/60");
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire_at(ip(1), "get_class_list", now), Ok(()));
+        }
+        // 每 20 秒恢复一次
+        assert_eq!(limiter.acquire_at(ip(1), "get_class_list", now), Err(20));
+        // 其他客户端有各自的桶
+        assert_eq!(limiter.acquire_at(ip(2), "get_class_list", now), Ok(()));
+    }
+
+    #[test]
+    fn refills_over_time_up_to_capacity() {
+        let limiter = limiter("default=3/60");
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire_at(ip(1), "get_class_list", start).unwrap();
+        }
+        assert_eq!(
+            limiter.acquire_at(ip(1), "get_class_list", start + Duration::from_secs(5)),
+            Err(15)
+        );
+        let refilled = start + Duration::from_secs(20);
+        assert_eq!(
+            limiter.acquire_at(ip(1), "get_class_list", refilled),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.acquire_at(ip(1), "get_class_list", refilled),
+            Err(20)
+        );
+
+        // 空闲再久也只恢复到容量
+        let idle = refilled + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire_at(ip(1), "get_class_list", idle), Ok(()));
+        }
+        assert!(limiter.acquire_at(ip(1), "get_class_list", idle).is_err());
+    }
+
+    #[test]
+    fn endpoints_use_their_own_limits_and_buckets() {
+        let limiter = limiter("save_signing_code=2/60,default=4/60");
+        let now = Instant::now();
+        for _ in 0..2 {
+            assert_eq!(limiter.acquire_at(ip(1), "save_signing_code", now), Ok(()));
+        }
+        assert_eq!(limiter.acquire_at(ip(1), "save_signing_code", now), Err(30));
+        for _ in 0..4 {
+            assert_eq!(limiter.acquire_at(ip(1), "get_class_list", now), Ok(()));
+        }
+        assert_eq!(limiter.acquire_at(ip(1), "get_class_list", now), Err(15));
+        // 未被覆盖的默认配置仍然生效
+        assert_eq!(limiter.limit_for("sign_in"), RateLimit::new(5, 60));
+
+        let stats: HashMap<String, (u64, u64)> = limiter
+            .counters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(endpoint, counter)| (endpoint.clone(), (counter.allowed, counter.rejected)))
+            .collect();
+        assert_eq!(stats["save_signing_code"], (2, 1));
+        assert_eq!(stats["get_class_list"], (4, 1));
+    }
+
+    #[test]
+    fn parses_rate_limit_config() {
+        assert_eq!("10/60".parse::<RateLimit>(), Ok(RateLimit::new(10, 60)));
+        assert_eq!(" 10 / 60 ".parse::<RateLimit>(), Ok(RateLimit::new(10, 60)));
+        for invalid in ["10", "0/60", "10/0", "-1/60", "a/b", ""] {
+            assert!(invalid.parse::<RateLimit>().is_err(), "{}", invalid);
+        }
+
+        assert!(RateLimiter::from_config(Some("off")).is_none());
+        assert!(RateLimiter::from_config(Some(" off ")).is_none());
+
+        let defaults = RateLimiter::from_config(None).unwrap();
+        assert_eq!(defaults.default_limit, RateLimit::new(120, 60));
+        assert_eq!(
+            defaults.limit_for("save_signing_code"),
+            RateLimit::new(20, 60)
+        );
+
+        // 无法解析的条目被忽略，其余条目照常生效
+        let limiter = limiter(" sign_in = 1/10 ,,bad,log_scan_result=0/60,default=abc,new_fn=7/70");
+        assert_eq!(limiter.limit_for("sign_in"), RateLimit::new(1, 10));
+        assert_eq!(limiter.limit_for("new_fn"), RateLimit::new(7, 70));
+        assert_eq!(limiter.limit_for("log_scan_result"), RateLimit::new(30, 60));
+        assert_eq!(limiter.default_limit, RateLimit::new(120, 60));
+        assert_eq!(limiter.limit_for("unknown"), RateLimit::new(120, 60));
+    }
+
+    #[test]
+    fn sweeps_full_buckets_once_the_table_grows() {
+        let limiter = limiter("default=2/60");
+        let start = Instant::now();
+        let clients: Vec<IpAddr> = (1..=MAX_TRACKED_BUCKETS as u32 + 1)
+            .map(|n| IpAddr::from(n.to_be_bytes()))
+            .collect();
+        // 该客户端用完了令牌，30 秒后仍未恢复满
+        let active = clients[0];
+        limiter.acquire_at(active, "get_class_list", start).unwrap();
+        for client in &clients {
+            limiter
+                .acquire_at(*client, "get_class_list", start)
+                .unwrap();
+        }
+        // 桶数刚好超过上限，下一次请求时才清理
+        assert_eq!(
+            limiter.buckets.lock().unwrap().entries.len(),
+            MAX_TRACKED_BUCKETS + 1
+        );
+
+        let later = start + Duration::from_secs(30);
+        limiter.acquire_at(ip(1), "get_class_list", later).unwrap();
+        {
+            let buckets = limiter.buckets.lock().unwrap();
+            assert_eq!(buckets.entries.len(), 2);
+            assert!(buckets
+                .entries
+                .contains_key(&(active, "get_class_list".to_string())));
+            assert_eq!(buckets.sweep_above, MAX_TRACKED_BUCKETS);
+        }
+        // 保留的桶状态不变
+        assert_eq!(limiter.acquire_at(active, "get_class_list", later), Ok(()));
+        assert_eq!(limiter.acquire_at(active, "get_class_list", later), Err(30));
+    }
+
+    fn request(peer: IpAddr, forwarded_for: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/api/get_class_list");
+        if let Some(value) = forwarded_for {
+            builder = builder.header("x-forwarded-for", value);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(peer, 40000)));
+        request
+    }
+
+    #[test]
+    fn trusts_forwarded_for_only_from_loopback() {
+        let loopback = IpAddr::from([127, 0, 0, 1]);
+        // 代理追加在末尾的地址才是客户端地址，前面的可能由客户端伪造
+        assert_eq!(
+            client_ip(&request(loopback, Some("198.51.100.7, 203.0.113.9"))),
+            Some(ip(9))
+        );
+        assert_eq!(
+            client_ip(&request(
+                std::net::Ipv6Addr::LOCALHOST.into(),
+                Some("203.0.113.9")
+            )),
+            Some(ip(9))
+        );
+        assert_eq!(client_ip(&request(loopback, None)), Some(loopback));
+        assert_eq!(
+            client_ip(&request(loopback, Some("not-an-ip"))),
+            Some(loopback)
+        );
+        // 直接连接的客户端不能通过请求头冒充其他地址
+        assert_eq!(client_ip(&request(ip(5), Some("203.0.113.9"))), Some(ip(5)));
+
+        let without_peer = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(client_ip(&without_peer), None);
+    }
+}
@@ -0,0 +1,1421 @@
+use crate::models::{
+    ClassData, ClassPage, ClassQuery, ClassSort, Lesson, SaveResult, SaveStatus, ScanRecord,
+};
+use crate::utils::db::{get_db, run_blocking, DbPool};
+use crate::utils::signing_code::ScanVerdict;
+use crate::utils::time::CheckTime;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// 选择数据存储后端的环境变量，设为 "memory" 时使用内存存储（重启后数据丢失）
+pub const DATA_BACKEND_ENV: &str = "DATA_BACKEND";
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    Storage(String),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::Storage(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<rusqlite::Error> for RepositoryError {
+    fn from(e: rusqlite::Error) -> Self {
+        RepositoryError::Storage(e.to_string())
+    }
+}
+
+// 扫码或手动保存时写入的数据，None 表示保持原值
+#[derive(Debug, Clone, Default)]
+pub struct ClassUpsert {
+    pub class_name: Option<String>,
+    pub classes: Option<String>,
+    pub checkwork_id: Option<String>,
+    pub class_lesson_id: Option<String>,
+    pub created_time: Option<CheckTime>,
+}
+
+// courses 表（课程）及其扫码、课次的访问接口
+pub trait ClassRepository: Send + Sync {
+    fn get_by_site_id(&self, site_id: &str) -> Result<Option<ClassData>, RepositoryError>;
+    fn get_by_id(&self, id: i64) -> Result<Option<ClassData>, RepositoryError>;
+    fn get_id_by_site_id(&self, site_id: &str) -> Result<Option<i64>, RepositoryError>;
+    fn get_by_share_slug(&self, slug: &str) -> Result<Option<ClassData>, RepositoryError>;
+    // 课程的分享 slug，课程不存在或分享链接已停用时返回 None
+    fn get_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError>;
+    // 按条件分页列出课程
+    fn list(&self, query: &ClassQuery) -> Result<ClassPage, RepositoryError>;
+    // 在一个事务中完成：课程不存在时新建，存在时更新非空字段，写入扫码数据时清除过期标记
+    fn upsert_scan(&self, site_id: &str, data: ClassUpsert) -> Result<SaveResult, RepositoryError>;
+    // 与 upsert_scan 相同，但写入前在同一事务中用已保存的签到码生成时间调用 check，
+    // check 拒绝时不写入任何数据
+    fn upsert_scan_checked(
+        &self,
+        site_id: &str,
+        data: ClassUpsert,
+        check: &dyn Fn(Option<&CheckTime>) -> Result<(), ScanVerdict>,
+    ) -> Result<Result<SaveResult, ScanVerdict>, RepositoryError>;
+    // 更新课程名称和班级，课程不存在时返回 false
+    fn update_info(
+        &self,
+        site_id: &str,
+        class_name: Option<String>,
+        classes: Option<String>,
+    ) -> Result<bool, RepositoryError>;
+    // 为课程生成新的分享 slug，原来的分享链接随之失效；课程不存在时返回 None
+    fn rotate_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError>;
+    // 停用课程的分享链接，课程不存在时返回 false
+    fn revoke_share_slug(&self, site_id: &str) -> Result<bool, RepositoryError>;
+    // 标记课程过期，课程不存在时返回 false
+    fn mark_expired(&self, site_id: &str) -> Result<bool, RepositoryError>;
+    // 将最新签到码生成时间早于 before 的未过期课程标记为过期，返回这些课程的 site_id
+    fn expire_stale(&self, before: CheckTime) -> Result<Vec<String>, RepositoryError>;
+    // 课程的扫码记录，最新的在前；课程不存在时返回空列表
+    fn list_scans(&self, site_id: &str) -> Result<Vec<ScanRecord>, RepositoryError>;
+    // 课程出现过的课次，最近出现的在前；课程不存在时返回空列表
+    fn list_lessons(&self, site_id: &str) -> Result<Vec<Lesson>, RepositoryError>;
+}
+
+static CLASS_REPOSITORY: OnceLock<Arc<dyn ClassRepository>> = OnceLock::new();
+
+pub fn class_repository() -> Arc<dyn ClassRepository> {
+    CLASS_REPOSITORY
+        .get_or_init(|| match std::env::var(DATA_BACKEND_ENV).as_deref() {
+            Ok("memory") => {
+                println!("Using in-memory class repository");
+                Arc::new(InMemoryClassRepository::default())
+            }
+            _ => Arc::new(SqliteClassRepository::new(get_db())),
+        })
+        .clone()
+}
+
+// 在阻塞线程中使用仓库，供服务端函数调用
+pub async fn with_class_repository<T, F>(f: F) -> Result<T, RepositoryError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn ClassRepository) -> Result<T, RepositoryError> + Send + 'static,
+{
+    let repository = class_repository();
+    run_blocking(move || f(repository.as_ref()))
+        .await
+        .map_err(|e| RepositoryError::Storage(format!("blocking task failed: {}", e)))?
+}
+
+fn default_class_name(site_id: &str) -> String {
+    format!("Course_{}", site_id)
+}
+
+fn default_classes() -> String {
+    "Unknown Class".to_string()
+}
+
+// 课程及其最新一次扫码，后面可接 WHERE / ORDER BY
+const CLASS_DATA_QUERY: &str = "SELECT c.id, c.site_id, c.class_name, c.classes, \
+    s.checkwork_id, s.class_lesson_id, s.created_time, c.is_expired, \
+    COALESCE(c.updated_at, c.created_at) \
+    FROM courses c LEFT JOIN scans s ON s.id = \
+    (SELECT id FROM scans WHERE class_id = c.id ORDER BY id DESC LIMIT 1)";
+
+fn class_data_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClassData> {
+    Ok(ClassData {
+        id: row.get(0)?,
+        site_id: row.get(1)?,
+        class_name: row.get(2)?,
+        classes: row.get(3)?,
+        last_checkwork_id: row.get(4)?,
+        last_class_lesson_id: row.get(5)?,
+        last_created_time: row.get(6)?,
+        is_expired: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+// 分享 slug 的随机字节数，编码为两倍长度的小写十六进制
+const SHARE_SLUG_BYTES: usize = 12;
+
+fn new_share_slug() -> rusqlite::Result<String> {
+    let mut bytes = [0u8; SHARE_SLUG_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(|e| {
+        rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::other(format!(
+            "failed to generate share slug: {}",
+            e
+        ))))
+    })?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+const DEFAULT_CLASS_PAGE_SIZE: u32 = 20;
+const MAX_CLASS_PAGE_SIZE: u32 = 100;
+
+// 查询实际使用的偏移和条数
+fn page_bounds(query: &ClassQuery) -> (u32, u32) {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CLASS_PAGE_SIZE)
+        .clamp(1, MAX_CLASS_PAGE_SIZE);
+    (query.offset.unwrap_or(0), limit)
+}
+
+pub struct SqliteClassRepository {
+    pool: Arc<DbPool>,
+}
+
+impl SqliteClassRepository {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+
+    fn read<T>(
+        &self,
+        f: impl FnOnce(&rusqlite::Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, RepositoryError> {
+        Ok(self.pool.read(f)?)
+    }
+
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, RepositoryError> {
+        Ok(self.pool.write(f)?)
+    }
+
+    fn query_class(
+        &self,
+        condition: &str,
+        param: &dyn rusqlite::ToSql,
+    ) -> Result<Option<ClassData>, RepositoryError> {
+        use rusqlite::OptionalExtension;
+        self.read(|conn| {
+            conn.query_row(
+                &format!("{} WHERE {}", CLASS_DATA_QUERY, condition),
+                [param],
+                class_data_from_row,
+            )
+            .optional()
+        })
+    }
+}
+
+// 课程不存在时返回 None，存在时返回其最近一次签到码的生成时间
+fn stored_scan_time(
+    tx: &rusqlite::Transaction,
+    site_id: &str,
+) -> rusqlite::Result<Option<Option<CheckTime>>> {
+    use rusqlite::OptionalExtension;
+    tx.query_row(
+        "SELECT (SELECT created_time FROM scans WHERE class_id = c.id ORDER BY id DESC LIMIT 1)
+         FROM courses c WHERE c.site_id = ?1",
+        rusqlite::params![site_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+// 新建或更新课程，只能在已获取写锁的事务中调用
+fn upsert_in_tx(
+    tx: &rusqlite::Transaction,
+    site_id: &str,
+    data: ClassUpsert,
+    existed: bool,
+) -> rusqlite::Result<SaveResult> {
+    let current_time = CheckTime::now();
+    let has_scan = data.checkwork_id.is_some()
+        || data.class_lesson_id.is_some()
+        || data.created_time.is_some();
+    // 分享 slug 只在新建课程时生成，更新时保留原值（包括已停用的 NULL）
+    let (id, share_slug) = tx.query_row(
+        "INSERT INTO courses (site_id, class_name, classes, is_expired, created_at, updated_at, share_slug)
+         VALUES (?1, COALESCE(?2, ?3), COALESCE(?4, ?5), 0, ?6, ?6, ?8)
+         ON CONFLICT(site_id) DO UPDATE SET
+             class_name = COALESCE(?2, class_name),
+             classes = COALESCE(?4, classes),
+             is_expired = CASE WHEN ?7 THEN 0 ELSE is_expired END,
+             updated_at = ?6
+         RETURNING id, share_slug",
+        rusqlite::params![
+            site_id,
+            data.class_name,
+            default_class_name(site_id),
+            data.classes,
+            default_classes(),
+            current_time,
+            has_scan,
+            // 已有课程走 ON CONFLICT 分支，不需要生成
+            if existed { None } else { Some(new_share_slug()?) }
+        ],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)),
+    )?;
+    let new_lesson = if has_scan {
+        record_scan(tx, id, data, current_time)?
+    } else {
+        false
+    };
+    Ok(SaveResult {
+        id,
+        status: if existed {
+            SaveStatus::Updated
+        } else {
+            SaveStatus::Created
+        },
+        saved_at: current_time,
+        new_lesson,
+        share_slug,
+    })
+}
+
+// 写入一次扫码及其课次，返回是否是该课程第一次出现的课次。
+// 未提供的字段沿用上一次扫码的值；重复扫同一个签到码不产生新的扫码记录
+fn record_scan(
+    tx: &rusqlite::Transaction,
+    course_id: i64,
+    data: ClassUpsert,
+    current_time: CheckTime,
+) -> rusqlite::Result<bool> {
+    use rusqlite::OptionalExtension;
+    let latest = tx
+        .query_row(
+            "SELECT checkwork_id, class_lesson_id, created_time FROM scans
+             WHERE class_id = ?1 ORDER BY id DESC LIMIT 1",
+            rusqlite::params![course_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<CheckTime>>(2)?,
+                ))
+            },
+        )
+        .optional()?
+        .unwrap_or_default();
+    let checkwork_id = data.checkwork_id.or(latest.0);
+    let class_lesson_id = data.class_lesson_id.or(latest.1);
+    let created_time = data.created_time.or(latest.2);
+
+    tx.execute(
+        "INSERT INTO scans (class_id, checkwork_id, class_lesson_id, created_time, scanned_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT DO NOTHING",
+        rusqlite::params![
+            course_id,
+            checkwork_id,
+            class_lesson_id,
+            created_time,
+            current_time
+        ],
+    )?;
+
+    let Some(class_lesson_id) = class_lesson_id else {
+        return Ok(false);
+    };
+    let new_lesson = tx.query_row(
+        "SELECT NOT EXISTS(SELECT 1 FROM lessons WHERE course_id = ?1 AND class_lesson_id = ?2)",
+        rusqlite::params![course_id, class_lesson_id],
+        |row| row.get::<_, bool>(0),
+    )?;
+    let lesson_id = tx.query_row(
+        "INSERT INTO lessons (course_id, class_lesson_id, first_seen, last_seen)
+         VALUES (?1, ?2, ?3, ?3)
+         ON CONFLICT(course_id, class_lesson_id) DO UPDATE SET last_seen = excluded.last_seen
+         RETURNING id",
+        rusqlite::params![course_id, class_lesson_id, current_time],
+        |row| row.get::<_, i64>(0),
+    )?;
+    if let Some(checkwork_id) = checkwork_id {
+        tx.execute(
+            "INSERT INTO lesson_checkworks (lesson_id, checkwork_id, first_seen)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT DO NOTHING",
+            rusqlite::params![lesson_id, checkwork_id, current_time],
+        )?;
+    }
+    Ok(new_lesson)
+}
+
+impl ClassRepository for SqliteClassRepository {
+    fn get_by_site_id(&self, site_id: &str) -> Result<Option<ClassData>, RepositoryError> {
+        self.query_class("c.site_id = ?1", &site_id)
+    }
+
+    fn get_by_id(&self, id: i64) -> Result<Option<ClassData>, RepositoryError> {
+        self.query_class("c.id = ?1", &id)
+    }
+
+    fn get_id_by_site_id(&self, site_id: &str) -> Result<Option<i64>, RepositoryError> {
+        use rusqlite::OptionalExtension;
+        self.read(|conn| {
+            conn.query_row(
+                "SELECT id FROM courses WHERE site_id = ?1",
+                rusqlite::params![site_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    fn get_by_share_slug(&self, slug: &str) -> Result<Option<ClassData>, RepositoryError> {
+        self.query_class("c.share_slug = ?1", &slug)
+    }
+
+    fn get_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError> {
+        use rusqlite::OptionalExtension;
+        self.read(|conn| {
+            conn.query_row(
+                "SELECT share_slug FROM courses WHERE site_id = ?1",
+                rusqlite::params![site_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()
+        })
+        .map(Option::flatten)
+    }
+
+    fn list(&self, query: &ClassQuery) -> Result<ClassPage, RepositoryError> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(is_expired) = query.is_expired {
+            conditions.push("c.is_expired = ?");
+            params.push(Box::new(is_expired));
+        }
+        if let Some(search) = &query.search {
+            // 用 instr 而不是 LIKE，避免输入中的 % 和 _ 被当作通配符
+            conditions.push("(instr(c.class_name, ?) > 0 OR instr(c.classes, ?) > 0)");
+            params.push(Box::new(search.clone()));
+            params.push(Box::new(search.clone()));
+        }
+        // 时间列都是定长的 CHECK_TIME_FORMAT（旧版本的 UTC 时间已由迁移 #7 改写），
+        // 按字符串比较和排序即按时间先后
+        if let Some(since) = query.updated_since {
+            conditions.push("COALESCE(c.updated_at, c.created_at) >= ?");
+            params.push(Box::new(since));
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sort_column = match query.sort {
+            ClassSort::UpdatedAt => "COALESCE(c.updated_at, c.created_at)",
+            ClassSort::LastScan => "s.created_time",
+            ClassSort::ClassName => "c.class_name",
+            ClassSort::Id => "c.id",
+        };
+        let direction = if query.ascending { "ASC" } else { "DESC" };
+        let (offset, limit) = page_bounds(query);
+        let sql = format!(
+            "{} {} ORDER BY {} {}, c.id {} LIMIT {} OFFSET {}",
+            CLASS_DATA_QUERY, where_clause, sort_column, direction, direction, limit, offset
+        );
+        let count_sql = format!("SELECT COUNT(*) FROM courses c {}", where_clause);
+
+        let (classes, total) = self.read(|conn| {
+            let params = rusqlite::params_from_iter(params.iter().map(|p| p.as_ref()));
+            let total: i64 = conn.query_row(&count_sql, params.clone(), |row| row.get(0))?;
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(params, class_data_from_row)?;
+            Ok((rows.collect::<rusqlite::Result<Vec<_>>>()?, total))
+        })?;
+        Ok(ClassPage {
+            classes,
+            total: total as u64,
+            offset,
+            limit,
+        })
+    }
+
+    fn upsert_scan(&self, site_id: &str, data: ClassUpsert) -> Result<SaveResult, RepositoryError> {
+        self.write(|conn| {
+            // IMMEDIATE 事务在开始时即获取写锁，判断是否存在与写入之间不会被其他写操作插入
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let existed = stored_scan_time(&tx, site_id)?.is_some();
+            let result = upsert_in_tx(&tx, site_id, data, existed)?;
+            tx.commit()?;
+            Ok(result)
+        })
+    }
+
+    fn upsert_scan_checked(
+        &self,
+        site_id: &str,
+        data: ClassUpsert,
+        check: &dyn Fn(Option<&CheckTime>) -> Result<(), ScanVerdict>,
+    ) -> Result<Result<SaveResult, ScanVerdict>, RepositoryError> {
+        self.write(|conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let stored = stored_scan_time(&tx, site_id)?;
+            // 校验失败时直接返回，事务在 drop 时回滚
+            if let Err(verdict) = check(stored.flatten().as_ref()) {
+                return Ok(Err(verdict));
+            }
+            let result = upsert_in_tx(&tx, site_id, data, stored.is_some())?;
+            tx.commit()?;
+            Ok(Ok(result))
+        })
+    }
+
+    fn update_info(
+        &self,
+        site_id: &str,
+        class_name: Option<String>,
+        classes: Option<String>,
+    ) -> Result<bool, RepositoryError> {
+        let current_time = CheckTime::now();
+        self.write(|conn| {
+            let updated_rows = conn.execute(
+                "UPDATE courses
+                 SET class_name = COALESCE(?1, class_name),
+                     classes = COALESCE(?2, classes),
+                     updated_at = ?3
+                 WHERE site_id = ?4",
+                rusqlite::params![class_name, classes, current_time, site_id],
+            )?;
+            Ok(updated_rows > 0)
+        })
+    }
+
+    fn rotate_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError> {
+        use rusqlite::OptionalExtension;
+        let slug = new_share_slug()?;
+        self.write(|conn| {
+            conn.query_row(
+                "UPDATE courses SET share_slug = ?1 WHERE site_id = ?2 RETURNING share_slug",
+                rusqlite::params![slug, site_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    fn revoke_share_slug(&self, site_id: &str) -> Result<bool, RepositoryError> {
+        self.write(|conn| {
+            let updated_rows = conn.execute(
+                "UPDATE courses SET share_slug = NULL WHERE site_id = ?1",
+                rusqlite::params![site_id],
+            )?;
+            Ok(updated_rows > 0)
+        })
+    }
+
+    fn mark_expired(&self, site_id: &str) -> Result<bool, RepositoryError> {
+        self.write(|conn| {
+            let updated_rows = conn.execute(
+                "UPDATE courses SET is_expired = 1 WHERE site_id = ?1",
+                rusqlite::params![site_id],
+            )?;
+            Ok(updated_rows > 0)
+        })
+    }
+
+    fn expire_stale(&self, before: CheckTime) -> Result<Vec<String>, RepositoryError> {
+        self.write(|conn| {
+            let mut stmt = conn.prepare(
+                "UPDATE courses SET is_expired = 1
+                 WHERE is_expired = 0
+                   AND (SELECT created_time FROM scans WHERE class_id = courses.id
+                        ORDER BY id DESC LIMIT 1) < ?1
+                 RETURNING site_id",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![before], |row| row.get(0))?;
+            rows.collect()
+        })
+    }
+
+    fn list_scans(&self, site_id: &str) -> Result<Vec<ScanRecord>, RepositoryError> {
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT s.id, s.checkwork_id, s.class_lesson_id, s.created_time, s.scanned_at
+                 FROM scans s JOIN courses c ON c.id = s.class_id
+                 WHERE c.site_id = ?1
+                 ORDER BY s.id DESC",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![site_id], |row| {
+                Ok(ScanRecord {
+                    id: row.get(0)?,
+                    checkwork_id: row.get(1)?,
+                    class_lesson_id: row.get(2)?,
+                    created_time: row.get(3)?,
+                    scanned_at: row.get(4)?,
+                })
+            })?;
+            rows.collect()
+        })
+    }
+
+    fn list_lessons(&self, site_id: &str) -> Result<Vec<Lesson>, RepositoryError> {
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT l.id, l.class_lesson_id, l.first_seen, l.last_seen,
+                        (SELECT group_concat(checkwork_id, ',') FROM
+                            (SELECT checkwork_id FROM lesson_checkworks
+                             WHERE lesson_id = l.id ORDER BY first_seen))
+                 FROM lessons l JOIN courses c ON c.id = l.course_id
+                 WHERE c.site_id = ?1
+                 ORDER BY l.last_seen DESC, l.id DESC",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![site_id], |row| {
+                let checkwork_ids: Option<String> = row.get(4)?;
+                Ok(Lesson {
+                    id: row.get(0)?,
+                    class_lesson_id: row.get(1)?,
+                    first_seen: row.get(2)?,
+                    last_seen: row.get(3)?,
+                    checkwork_ids: checkwork_ids
+                        .map(|ids| ids.split(',').map(str::to_string).collect())
+                        .unwrap_or_default(),
+                })
+            })?;
+            rows.collect()
+        })
+    }
+}
+
+struct StoredClass {
+    // last_* 字段不在这里维护，读取时由 scans 得出
+    data: ClassData,
+    share_slug: Option<String>,
+    scans: Vec<ScanRecord>,
+    lessons: Vec<Lesson>,
+}
+
+impl StoredClass {
+    fn class_data(&self) -> ClassData {
+        let latest = self.scans.last();
+        ClassData {
+            last_checkwork_id: latest.and_then(|scan| scan.checkwork_id.clone()),
+            last_class_lesson_id: latest.and_then(|scan| scan.class_lesson_id.clone()),
+            last_created_time: latest.and_then(|scan| scan.created_time),
+            ..self.data.clone()
+        }
+    }
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    next_id: i64,
+    next_scan_id: i64,
+    next_lesson_id: i64,
+    // 以 site_id 为键
+    classes: HashMap<String, StoredClass>,
+}
+
+// 不落盘的实现，数据只保存在进程内存中
+#[derive(Default)]
+pub struct InMemoryClassRepository {
+    state: Mutex<InMemoryState>,
+}
+
+impl InMemoryState {
+    // share_slug 只在新建课程时使用
+    fn upsert(&mut self, site_id: &str, data: ClassUpsert) -> rusqlite::Result<SaveResult> {
+        let current_time = CheckTime::now();
+        let has_scan = data.checkwork_id.is_some()
+            || data.class_lesson_id.is_some()
+            || data.created_time.is_some();
+        let existed = self.classes.contains_key(site_id);
+        // 分享 slug 只在新建课程时生成
+        let share_slug = if existed {
+            None
+        } else {
+            self.next_id += 1;
+            Some(new_share_slug()?)
+        };
+        let next_id = self.next_id;
+        let stored = self
+            .classes
+            .entry(site_id.to_string())
+            .or_insert_with(|| StoredClass {
+                data: ClassData {
+                    id: next_id,
+                    site_id: site_id.to_string(),
+                    class_name: default_class_name(site_id),
+                    classes: default_classes(),
+                    last_checkwork_id: None,
+                    last_class_lesson_id: None,
+                    last_created_time: None,
+                    is_expired: false,
+                    updated_at: current_time,
+                },
+                share_slug,
+                scans: Vec::new(),
+                lessons: Vec::new(),
+            });
+
+        if let Some(class_name) = data.class_name {
+            stored.data.class_name = class_name;
+        }
+        if let Some(classes) = data.classes {
+            stored.data.classes = classes;
+        }
+        let mut new_lesson = false;
+        if has_scan {
+            stored.data.is_expired = false;
+            // 未提供的字段沿用上一次扫码的值；重复扫同一个签到码不产生新记录
+            let latest = stored.scans.last();
+            let checkwork_id = data
+                .checkwork_id
+                .or_else(|| latest.and_then(|scan| scan.checkwork_id.clone()));
+            let class_lesson_id = data
+                .class_lesson_id
+                .or_else(|| latest.and_then(|scan| scan.class_lesson_id.clone()));
+            let created_time = data
+                .created_time
+                .or_else(|| latest.and_then(|scan| scan.created_time));
+            let duplicate = stored.scans.iter().any(|scan| {
+                scan.checkwork_id == checkwork_id
+                    && scan.class_lesson_id == class_lesson_id
+                    && scan.created_time == created_time
+            });
+            if !duplicate {
+                self.next_scan_id += 1;
+                stored.scans.push(ScanRecord {
+                    id: self.next_scan_id,
+                    checkwork_id: checkwork_id.clone(),
+                    class_lesson_id: class_lesson_id.clone(),
+                    created_time,
+                    scanned_at: current_time,
+                });
+            }
+
+            if let Some(class_lesson_id) = class_lesson_id {
+                let position = stored
+                    .lessons
+                    .iter()
+                    .position(|lesson| lesson.class_lesson_id == class_lesson_id);
+                let lesson = match position {
+                    Some(index) => &mut stored.lessons[index],
+                    None => {
+                        new_lesson = true;
+                        self.next_lesson_id += 1;
+                        stored.lessons.push(Lesson {
+                            id: self.next_lesson_id,
+                            class_lesson_id,
+                            first_seen: current_time,
+                            last_seen: current_time,
+                            checkwork_ids: Vec::new(),
+                        });
+                        stored.lessons.last_mut().unwrap()
+                    }
+                };
+                lesson.last_seen = current_time;
+                if let Some(checkwork_id) = checkwork_id {
+                    if !lesson.checkwork_ids.contains(&checkwork_id) {
+                        lesson.checkwork_ids.push(checkwork_id);
+                    }
+                }
+            }
+        }
+        stored.data.updated_at = current_time;
+
+        Ok(SaveResult {
+            id: stored.data.id,
+            status: if existed {
+                SaveStatus::Updated
+            } else {
+                SaveStatus::Created
+            },
+            saved_at: current_time,
+            new_lesson,
+            share_slug: stored.share_slug.clone(),
+        })
+    }
+}
+
+impl InMemoryClassRepository {
+    fn with_state<T>(&self, f: impl FnOnce(&mut InMemoryState) -> T) -> Result<T, RepositoryError> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| RepositoryError::Storage(format!("lock poisoned: {}", e)))?;
+        Ok(f(&mut state))
+    }
+}
+
+impl ClassRepository for InMemoryClassRepository {
+    fn get_by_site_id(&self, site_id: &str) -> Result<Option<ClassData>, RepositoryError> {
+        self.with_state(|state| state.classes.get(site_id).map(StoredClass::class_data))
+    }
+
+    fn get_by_id(&self, id: i64) -> Result<Option<ClassData>, RepositoryError> {
+        self.with_state(|state| {
+            state
+                .classes
+                .values()
+                .find(|stored| stored.data.id == id)
+                .map(StoredClass::class_data)
+        })
+    }
+
+    fn get_id_by_site_id(&self, site_id: &str) -> Result<Option<i64>, RepositoryError> {
+        self.with_state(|state| state.classes.get(site_id).map(|stored| stored.data.id))
+    }
+
+    fn get_by_share_slug(&self, slug: &str) -> Result<Option<ClassData>, RepositoryError> {
+        self.with_state(|state| {
+            state
+                .classes
+                .values()
+                .find(|stored| stored.share_slug.as_deref() == Some(slug))
+                .map(StoredClass::class_data)
+        })
+    }
+
+    fn get_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError> {
+        self.with_state(|state| {
+            state
+                .classes
+                .get(site_id)
+                .and_then(|stored| stored.share_slug.clone())
+        })
+    }
+
+    fn list(&self, query: &ClassQuery) -> Result<ClassPage, RepositoryError> {
+        let (offset, limit) = page_bounds(query);
+        self.with_state(|state| {
+            let mut classes: Vec<ClassData> = state
+                .classes
+                .values()
+                .map(StoredClass::class_data)
+                .filter(|class| {
+                    query
+                        .is_expired
+                        .is_none_or(|is_expired| class.is_expired == is_expired)
+                        && query.search.as_ref().is_none_or(|search| {
+                            class.class_name.contains(search.as_str())
+                                || class.classes.contains(search.as_str())
+                        })
+                        && query
+                            .updated_since
+                            .is_none_or(|since| class.updated_at >= since)
+                })
+                .collect();
+            // 与 SQLite 实现一致：按排序字段排序，相同时按 id，两者方向相同
+            classes.sort_by(|a, b| {
+                let ordering = match query.sort {
+                    ClassSort::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+                    ClassSort::LastScan => a.last_created_time.cmp(&b.last_created_time),
+                    ClassSort::ClassName => a.class_name.cmp(&b.class_name),
+                    ClassSort::Id => std::cmp::Ordering::Equal,
+                };
+                ordering.then(a.id.cmp(&b.id))
+            });
+            if !query.ascending {
+                classes.reverse();
+            }
+            ClassPage {
+                total: classes.len() as u64,
+                classes: classes
+                    .into_iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .collect(),
+                offset,
+                limit,
+            }
+        })
+    }
+
+    fn upsert_scan(&self, site_id: &str, data: ClassUpsert) -> Result<SaveResult, RepositoryError> {
+        Ok(self.with_state(|state| state.upsert(site_id, data))??)
+    }
+
+    fn upsert_scan_checked(
+        &self,
+        site_id: &str,
+        data: ClassUpsert,
+        check: &dyn Fn(Option<&CheckTime>) -> Result<(), ScanVerdict>,
+    ) -> Result<Result<SaveResult, ScanVerdict>, RepositoryError> {
+        let result = self.with_state(|state| {
+            let stored_time = state
+                .classes
+                .get(site_id)
+                .and_then(|stored| stored.scans.last())
+                .and_then(|scan| scan.created_time);
+            if let Err(verdict) = check(stored_time.as_ref()) {
+                return Ok(Err(verdict));
+            }
+            state.upsert(site_id, data).map(Ok)
+        })?;
+        Ok(result?)
+    }
+
+    fn update_info(
+        &self,
+        site_id: &str,
+        class_name: Option<String>,
+        classes: Option<String>,
+    ) -> Result<bool, RepositoryError> {
+        let current_time = CheckTime::now();
+        self.with_state(|state| match state.classes.get_mut(site_id) {
+            Some(stored) => {
+                if let Some(class_name) = class_name {
+                    stored.data.class_name = class_name;
+                }
+                if let Some(classes) = classes {
+                    stored.data.classes = classes;
+                }
+                stored.data.updated_at = current_time;
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn rotate_share_slug(&self, site_id: &str) -> Result<Option<String>, RepositoryError> {
+        let slug = new_share_slug()?;
+        self.with_state(|state| {
+            state.classes.get_mut(site_id).map(|stored| {
+                stored.share_slug = Some(slug.clone());
+                slug
+            })
+        })
+    }
+
+    fn revoke_share_slug(&self, site_id: &str) -> Result<bool, RepositoryError> {
+        self.with_state(|state| match state.classes.get_mut(site_id) {
+            Some(stored) => {
+                stored.share_slug = None;
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn mark_expired(&self, site_id: &str) -> Result<bool, RepositoryError> {
+        self.with_state(|state| match state.classes.get_mut(site_id) {
+            Some(stored) => {
+                stored.data.is_expired = true;
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn expire_stale(&self, before: CheckTime) -> Result<Vec<String>, RepositoryError> {
+        self.with_state(|state| {
+            let mut expired = Vec::new();
+            for (site_id, stored) in state.classes.iter_mut() {
+                let latest = stored.scans.last().and_then(|scan| scan.created_time);
+                if !stored.data.is_expired && latest.is_some_and(|time| time < before) {
+                    stored.data.is_expired = true;
+                    expired.push(site_id.clone());
+                }
+            }
+            expired
+        })
+    }
+
+    fn list_scans(&self, site_id: &str) -> Result<Vec<ScanRecord>, RepositoryError> {
+        self.with_state(|state| {
+            state
+                .classes
+                .get(site_id)
+                .map(|stored| stored.scans.iter().rev().cloned().collect())
+                .unwrap_or_default()
+        })
+    }
+
+    fn list_lessons(&self, site_id: &str) -> Result<Vec<Lesson>, RepositoryError> {
+        self.with_state(|state| {
+            let mut lessons = state
+                .classes
+                .get(site_id)
+                .map(|stored| stored.lessons.clone())
+                .unwrap_or_default();
+            lessons.sort_by_key(|lesson| std::cmp::Reverse((lesson.last_seen, lesson.id)));
+            lessons
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::migrations::{run_migrations, MigrationMode};
+
+    fn sqlite_repository() -> SqliteClassRepository {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute("PRAGMA foreign_keys=ON", []).unwrap();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+        SqliteClassRepository::new(Arc::new(DbPool::single(conn)))
+    }
+
+    // 每个用例对两种实现各执行一次
+    fn repositories() -> Vec<(&'static str, Box<dyn ClassRepository>)> {
+        vec![
+            ("sqlite", Box::new(sqlite_repository())),
+            ("memory", Box::new(InMemoryClassRepository::default())),
+        ]
+    }
+
+    fn time(s: &str) -> CheckTime {
+        s.parse().unwrap()
+    }
+
+    fn scan(lesson: &str, checkwork: &str, created_time: &str) -> ClassUpsert {
+        ClassUpsert {
+            checkwork_id: Some(checkwork.to_string()),
+            class_lesson_id: Some(lesson.to_string()),
+            created_time: Some(time(created_time)),
+            ..Default::default()
+        }
+    }
+
+    fn named(class_name: &str, classes: &str) -> ClassUpsert {
+        ClassUpsert {
+            class_name: Some(class_name.to_string()),
+            classes: Some(classes.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn site_ids(page: &ClassPage) -> Vec<&str> {
+        page.classes
+            .iter()
+            .map(|class| class.site_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn upsert_creates_then_updates() {
+        for (backend, repo) in repositories() {
+            let created = repo.upsert_scan("site-1", ClassUpsert::default()).unwrap();
+            assert_eq!(created.status, SaveStatus::Created, "{}", backend);
+            assert!(!created.new_lesson, "{}", backend);
+            assert!(created.share_slug.is_some(), "{}", backend);
+
+            let class = repo.get_by_site_id("site-1").unwrap().unwrap();
+            assert_eq!(class.id, created.id, "{}", backend);
+            assert_eq!(
+                class.class_name,
+                default_class_name("site-1"),
+                "{}",
+                backend
+            );
+            assert_eq!(class.classes, default_classes(), "{}", backend);
+            assert_eq!(class.last_created_time, None, "{}", backend);
+
+            let updated = repo
+                .upsert_scan(
+                    "site-1",
+                    ClassUpsert {
+                        class_name: Some("数学".to_string()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(updated.status, SaveStatus::Updated, "{}", backend);
+            assert_eq!(updated.id, created.id, "{}", backend);
+            assert_eq!(updated.share_slug, created.share_slug, "{}", backend);
+
+            let class = repo.get_by_id(created.id).unwrap().unwrap();
+            assert_eq!(class.class_name, "数学", "{}", backend);
+            assert_eq!(class.classes, default_classes(), "{}", backend);
+            assert_eq!(
+                repo.get_id_by_site_id("site-1").unwrap(),
+                Some(created.id),
+                "{}",
+                backend
+            );
+            assert_eq!(repo.get_by_site_id("missing").unwrap(), None, "{}", backend);
+        }
+    }
+
+    #[test]
+    fn scan_upsert_clears_expired_and_info_upsert_keeps_it() {
+        for (backend, repo) in repositories() {
+            repo.upsert_scan("site-1", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            assert!(repo.mark_expired("site-1").unwrap(), "{}", backend);
+            assert!(!repo.mark_expired("missing").unwrap(), "{}", backend);
+
+            repo.upsert_scan("site-1", named("数学", "一班")).unwrap();
+            assert!(repo.get_by_site_id("site-1").unwrap().unwrap().is_expired);
+
+            repo.upsert_scan("site-1", scan("L1", "C2", "2024-03-01T08:10:00.000"))
+                .unwrap();
+            assert!(!repo.get_by_site_id("site-1").unwrap().unwrap().is_expired);
+        }
+    }
+
+    #[test]
+    fn checked_upsert_writes_nothing_when_rejected() {
+        for (backend, repo) in repositories() {
+            repo.upsert_scan("site-1", scan("L1", "C1", "2024-03-01T08:10:00.000"))
+                .unwrap();
+            let result = repo
+                .upsert_scan_checked(
+                    "site-1",
+                    scan("L2", "C2", "2024-03-01T08:00:00.000"),
+                    &|stored| {
+                        assert_eq!(stored, Some(&time("2024-03-01T08:10:00.000")));
+                        Err(ScanVerdict::Stale {
+                            scanned: time("2024-03-01T08:00:00.000"),
+                            stored: *stored.unwrap(),
+                        })
+                    },
+                )
+                .unwrap();
+            assert!(result.is_err(), "{}", backend);
+            assert_eq!(repo.list_scans("site-1").unwrap().len(), 1, "{}", backend);
+            assert_eq!(repo.list_lessons("site-1").unwrap().len(), 1, "{}", backend);
+
+            let result = repo
+                .upsert_scan_checked(
+                    "site-2",
+                    scan("L1", "C1", "2024-03-01T08:00:00.000"),
+                    &|stored| {
+                        assert_eq!(stored, None);
+                        Ok(())
+                    },
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!(result.status, SaveStatus::Created, "{}", backend);
+        }
+    }
+
+    #[test]
+    fn update_info_changes_only_given_fields() {
+        for (backend, repo) in repositories() {
+            repo.upsert_scan("site-1", named("数学", "一班")).unwrap();
+            assert!(repo
+                .update_info("site-1", None, Some("二班".to_string()))
+                .unwrap());
+            assert!(!repo
+                .update_info("missing", Some("语文".to_string()), None)
+                .unwrap());
+            let class = repo.get_by_site_id("site-1").unwrap().unwrap();
+            assert_eq!(class.class_name, "数学", "{}", backend);
+            assert_eq!(class.classes, "二班", "{}", backend);
+        }
+    }
+
+    #[test]
+    fn list_filters_sorts_and_pages() {
+        for (backend, repo) in repositories() {
+            repo.upsert_scan("a", named("数学", "一班")).unwrap();
+            repo.upsert_scan("b", named("语文", "二班")).unwrap();
+            repo.upsert_scan("c", named("英语", "一班")).unwrap();
+            repo.upsert_scan("a", scan("L1", "C1", "2024-03-01T09:00:00.000"))
+                .unwrap();
+            repo.upsert_scan("c", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            repo.mark_expired("b").unwrap();
+
+            let all = repo.list(&ClassQuery::default()).unwrap();
+            assert_eq!(all.total, 3, "{}", backend);
+
+            let query = ClassQuery {
+                is_expired: Some(false),
+                sort: ClassSort::Id,
+                ascending: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                site_ids(&repo.list(&query).unwrap()),
+                ["a", "c"],
+                "{}",
+                backend
+            );
+
+            let query = ClassQuery {
+                search: Some("一班".to_string()),
+                sort: ClassSort::Id,
+                ..Default::default()
+            };
+            assert_eq!(
+                site_ids(&repo.list(&query).unwrap()),
+                ["c", "a"],
+                "{}",
+                backend
+            );
+
+            let query = ClassQuery {
+                sort: ClassSort::ClassName,
+                ascending: true,
+                ..Default::default()
+            };
+            let mut expected = [("数学", "a"), ("英语", "c"), ("语文", "b")];
+            expected.sort();
+            let expected: Vec<&str> = expected.iter().map(|(_, site_id)| *site_id).collect();
+            assert_eq!(
+                site_ids(&repo.list(&query).unwrap()),
+                expected,
+                "{}",
+                backend
+            );
+
+            // 没有扫码记录的课程升序时排在最前
+            let query = ClassQuery {
+                sort: ClassSort::LastScan,
+                ascending: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                site_ids(&repo.list(&query).unwrap()),
+                ["b", "c", "a"],
+                "{}",
+                backend
+            );
+
+            let query = ClassQuery {
+                sort: ClassSort::Id,
+                ascending: true,
+                offset: Some(1),
+                limit: Some(1),
+                ..Default::default()
+            };
+            let page = repo.list(&query).unwrap();
+            assert_eq!(site_ids(&page), ["b"], "{}", backend);
+            assert_eq!(
+                (page.total, page.offset, page.limit),
+                (3, 1, 1),
+                "{}",
+                backend
+            );
+
+            let query = ClassQuery {
+                limit: Some(1000),
+                ..Default::default()
+            };
+            assert_eq!(
+                repo.list(&query).unwrap().limit,
+                MAX_CLASS_PAGE_SIZE,
+                "{}",
+                backend
+            );
+
+            let query = ClassQuery {
+                updated_since: Some(CheckTime::now() - chrono::Duration::hours(1)),
+                ..Default::default()
+            };
+            assert_eq!(repo.list(&query).unwrap().total, 3, "{}", backend);
+            let query = ClassQuery {
+                updated_since: Some(CheckTime::now() + chrono::Duration::hours(1)),
+                ..Default::default()
+            };
+            assert_eq!(repo.list(&query).unwrap().total, 0, "{}", backend);
+        }
+    }
+
+    // 迁移 #7 之前写入的 UTC 时间按字符串比较会排错，迁移后与新写入的时间一起正确排序
+    #[test]
+    fn list_orders_legacy_timestamps_after_migration() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+        conn.execute_batch(
+            "INSERT INTO courses (site_id, class_name, classes, created_at, updated_at)
+             VALUES ('legacy', '数学', '一班', '2024-03-01 19:00:00', '2024-03-01 20:00:00'),
+                    ('current', '语文', '二班', '2024-03-02T00:00:00.000', '2024-03-02T01:00:00.000');
+             DELETE FROM schema_migrations WHERE version >= 7;
+             PRAGMA user_version = 6;",
+        )
+        .unwrap();
+        run_migrations(&mut conn, MigrationMode::Apply).unwrap();
+        let repo = SqliteClassRepository::new(Arc::new(DbPool::single(conn)));
+
+        let legacy = repo.get_by_site_id("legacy").unwrap().unwrap();
+        assert_eq!(legacy.updated_at, time("2024-03-02T04:00:00.000"));
+        let page = repo.list(&ClassQuery::default()).unwrap();
+        assert_eq!(site_ids(&page), ["legacy", "current"]);
+        let query = ClassQuery {
+            updated_since: Some(time("2024-03-02T02:00:00.000")),
+            ..Default::default()
+        };
+        assert_eq!(site_ids(&repo.list(&query).unwrap()), ["legacy"]);
+    }
+
+    #[test]
+    fn record_scan_tracks_scans_and_lessons() {
+        for (backend, repo) in repositories() {
+            let first = repo
+                .upsert_scan("site-1", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            assert!(first.new_lesson, "{}", backend);
+            // 重复扫同一个签到码不产生新的扫码记录
+            let repeated = repo
+                .upsert_scan("site-1", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            assert!(!repeated.new_lesson, "{}", backend);
+            let same_lesson = repo
+                .upsert_scan("site-1", scan("L1", "C2", "2024-03-01T08:05:00.000"))
+                .unwrap();
+            assert!(!same_lesson.new_lesson, "{}", backend);
+            // 未提供的字段沿用上一次扫码的值
+            let next_lesson = repo
+                .upsert_scan(
+                    "site-1",
+                    ClassUpsert {
+                        class_lesson_id: Some("L2".to_string()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert!(next_lesson.new_lesson, "{}", backend);
+
+            let scans = repo.list_scans("site-1").unwrap();
+            assert_eq!(scans.len(), 3, "{}", backend);
+            assert_eq!(
+                scans[0].class_lesson_id.as_deref(),
+                Some("L2"),
+                "{}",
+                backend
+            );
+            assert_eq!(scans[0].checkwork_id.as_deref(), Some("C2"), "{}", backend);
+            assert_eq!(
+                scans[0].created_time,
+                Some(time("2024-03-01T08:05:00.000")),
+                "{}",
+                backend
+            );
+            assert_eq!(scans[2].checkwork_id.as_deref(), Some("C1"), "{}", backend);
+
+            let class = repo.get_by_site_id("site-1").unwrap().unwrap();
+            assert_eq!(
+                class.last_class_lesson_id.as_deref(),
+                Some("L2"),
+                "{}",
+                backend
+            );
+            assert_eq!(
+                class.last_checkwork_id.as_deref(),
+                Some("C2"),
+                "{}",
+                backend
+            );
+
+            let lessons = repo.list_lessons("site-1").unwrap();
+            let lessons: Vec<(&str, Vec<&str>)> = lessons
+                .iter()
+                .map(|lesson| {
+                    (
+                        lesson.class_lesson_id.as_str(),
+                        lesson.checkwork_ids.iter().map(String::as_str).collect(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                lessons,
+                [("L2", vec!["C2"]), ("L1", vec!["C1", "C2"])],
+                "{}",
+                backend
+            );
+            assert!(
+                repo.list_scans("missing").unwrap().is_empty(),
+                "{}",
+                backend
+            );
+            assert!(
+                repo.list_lessons("missing").unwrap().is_empty(),
+                "{}",
+                backend
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_scan_without_lesson_id_is_recorded_once() {
+        for (backend, repo) in repositories() {
+            let partial = || ClassUpsert {
+                checkwork_id: Some("C1".to_string()),
+                created_time: Some(time("2024-03-01T08:00:00.000")),
+                ..Default::default()
+            };
+            repo.upsert_scan("site-1", partial()).unwrap();
+            repo.upsert_scan("site-1", partial()).unwrap();
+
+            let scans = repo.list_scans("site-1").unwrap();
+            assert_eq!(scans.len(), 1, "{}", backend);
+            assert_eq!(scans[0].class_lesson_id, None, "{}", backend);
+            assert!(
+                repo.list_lessons("site-1").unwrap().is_empty(),
+                "{}",
+                backend
+            );
+        }
+    }
+
+    #[test]
+    fn expire_stale_marks_only_old_active_classes() {
+        for (backend, repo) in repositories() {
+            repo.upsert_scan("old", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            repo.upsert_scan("new", scan("L1", "C1", "2024-03-01T12:00:00.000"))
+                .unwrap();
+            repo.upsert_scan("expired", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            repo.mark_expired("expired").unwrap();
+            repo.upsert_scan("no-scan", ClassUpsert::default()).unwrap();
+
+            let before = time("2024-03-01T10:00:00.000");
+            assert_eq!(repo.expire_stale(before).unwrap(), ["old"], "{}", backend);
+            assert!(repo.get_by_site_id("old").unwrap().unwrap().is_expired);
+            assert!(!repo.get_by_site_id("new").unwrap().unwrap().is_expired);
+            assert!(!repo.get_by_site_id("no-scan").unwrap().unwrap().is_expired);
+            assert!(repo.expire_stale(before).unwrap().is_empty(), "{}", backend);
+        }
+    }
+
+    #[test]
+    fn share_slug_rotates_and_revokes() {
+        for (backend, repo) in repositories() {
+            let original = repo
+                .upsert_scan("site-1", ClassUpsert::default())
+                .unwrap()
+                .share_slug
+                .unwrap();
+            assert_eq!(original.len(), SHARE_SLUG_BYTES * 2, "{}", backend);
+            let class = repo.get_by_share_slug(&original).unwrap().unwrap();
+            assert_eq!(class.site_id, "site-1", "{}", backend);
+
+            let rotated = repo.rotate_share_slug("site-1").unwrap().unwrap();
+            assert_ne!(rotated, original, "{}", backend);
+            assert_eq!(
+                repo.get_by_share_slug(&original).unwrap(),
+                None,
+                "{}",
+                backend
+            );
+            assert!(
+                repo.get_by_share_slug(&rotated).unwrap().is_some(),
+                "{}",
+                backend
+            );
+            assert_eq!(
+                repo.get_share_slug("site-1").unwrap(),
+                Some(rotated.clone()),
+                "{}",
+                backend
+            );
+            assert_eq!(repo.get_share_slug("missing").unwrap(), None, "{}", backend);
+            assert_eq!(
+                repo.rotate_share_slug("missing").unwrap(),
+                None,
+                "{}",
+                backend
+            );
+
+            assert!(repo.revoke_share_slug("site-1").unwrap(), "{}", backend);
+            assert!(!repo.revoke_share_slug("missing").unwrap(), "{}", backend);
+            assert_eq!(
+                repo.get_by_share_slug(&rotated).unwrap(),
+                None,
+                "{}",
+                backend
+            );
+            // 停用后再次保存不会重新生成分享链接
+            let saved = repo
+                .upsert_scan("site-1", scan("L1", "C1", "2024-03-01T08:00:00.000"))
+                .unwrap();
+            assert_eq!(saved.share_slug, None, "{}", backend);
+            assert_eq!(repo.get_share_slug("site-1").unwrap(), None, "{}", backend);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use crate::models::{ScanLogEntry, ScanLogQuery, ScanSource};
+use crate::utils::db::{get_db, run_blocking, DbPool};
+use crate::utils::repository::{RepositoryError, DATA_BACKEND_ENV};
+use crate::utils::signing_code::ScanVerdict;
+use crate::utils::time::CheckTime;
+use chrono::Duration;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// 扫码日志保留天数的环境变量，设为 0 时不清理
+const SCAN_LOG_RETENTION_DAYS_ENV: &str = "SCAN_LOG_RETENTION_DAYS";
+const DEFAULT_SCAN_LOG_RETENTION_DAYS: i64 = 90;
+
+const DEFAULT_SCAN_LOG_LIMIT: u32 = 100;
+const MAX_SCAN_LOG_LIMIT: u32 = 1000;
+
+// 待写入的扫码日志，记录时间由写入时决定
+#[derive(Debug, Clone)]
+pub struct NewScanLog {
+    pub raw: String,
+    pub site_id: Option<String>,
+    pub verdict: ScanVerdict,
+    pub source: ScanSource,
+    pub user_agent: Option<String>,
+}
+
+// scan_logs 表的访问接口
+pub trait ScanLogRepository: Send + Sync {
+    fn append(&self, log: NewScanLog) -> Result<(), RepositoryError>;
+    // 按记录时间倒序返回符合条件的日志
+    fn query(&self, query: &ScanLogQuery) -> Result<Vec<ScanLogEntry>, RepositoryError>;
+    // 删除早于 before 的日志，返回删除的条数
+    fn prune(&self, before: CheckTime) -> Result<usize, RepositoryError>;
+}
+
+static SCAN_LOG_REPOSITORY: OnceLock<Arc<dyn ScanLogRepository>> = OnceLock::new();
+
+pub fn scan_log_repository() -> Arc<dyn ScanLogRepository> {
+    SCAN_LOG_REPOSITORY
+        .get_or_init(|| match std::env::var(DATA_BACKEND_ENV).as_deref() {
+            Ok("memory") => Arc::new(InMemoryScanLogRepository::default()),
+            _ => Arc::new(SqliteScanLogRepository::new(get_db())),
+        })
+        .clone()
+}
+
+fn retention_days() -> i64 {
+    std::env::var(SCAN_LOG_RETENTION_DAYS_ENV)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_SCAN_LOG_RETENTION_DAYS)
+}
+
+// 写入一条扫码日志，并清理超过保留期的日志
+pub async fn record_scan_log(log: NewScanLog) -> Result<(), RepositoryError> {
+    let repository = scan_log_repository();
+    run_blocking(move || {
+        repository.append(log)?;
+        let days = retention_days();
+        if days > 0 {
+            repository.prune(CheckTime::now() - Duration::days(days))?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| RepositoryError::Storage(format!("blocking task failed: {}", e)))?
+}
+
+pub async fn query_scan_logs(query: ScanLogQuery) -> Result<Vec<ScanLogEntry>, RepositoryError> {
+    let repository = scan_log_repository();
+    run_blocking(move || repository.query(&query))
+        .await
+        .map_err(|e| RepositoryError::Storage(format!("blocking task failed: {}", e)))?
+}
+
+fn query_limit(query: &ScanLogQuery) -> u32 {
+    query
+        .limit
+        .unwrap_or(DEFAULT_SCAN_LOG_LIMIT)
+        .min(MAX_SCAN_LOG_LIMIT)
+}
+
+pub struct SqliteScanLogRepository {
+    pool: Arc<DbPool>,
+}
+
+impl SqliteScanLogRepository {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+}
+
+fn to_sql_error(e: impl std::error::Error + Send + Sync + 'static) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+fn from_sql_error(
+    column: usize,
+    e: impl std::error::Error + Send + Sync + 'static,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(e))
+}
+
+fn scan_log_from_row(row: &rusqlite::Row) -> rusqlite::Result<ScanLogEntry> {
+    let verdict: String = row.get(4)?;
+    let source: String = row.get(5)?;
+    Ok(ScanLogEntry {
+        id: row.get(0)?,
+        logged_at: row.get(1)?,
+        raw: row.get(2)?,
+        site_id: row.get(3)?,
+        verdict: serde_json::from_str(&verdict).map_err(|e| from_sql_error(4, e))?,
+        source: source
+            .parse()
+            .map_err(|e: String| from_sql_error(5, std::io::Error::other(e)))?,
+        user_agent: row.get(6)?,
+    })
+}
+
+impl ScanLogRepository for SqliteScanLogRepository {
+    fn append(&self, log: NewScanLog) -> Result<(), RepositoryError> {
+        let verdict = serde_json::to_string(&log.verdict).map_err(to_sql_error)?;
+        self.pool.write(|conn| {
+            conn.execute(
+                "INSERT INTO scan_logs (logged_at, raw, site_id, verdict_kind, verdict, source, user_agent)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    CheckTime::now(),
+                    log.raw,
+                    log.site_id,
+                    log.verdict.kind(),
+                    verdict,
+                    log.source.as_str(),
+                    log.user_agent
+                ],
+            )?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn query(&self, query: &ScanLogQuery) -> Result<Vec<ScanLogEntry>, RepositoryError> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(site_id) = &query.site_id {
+            conditions.push("site_id = ?");
+            params.push(Box::new(site_id.clone()));
+        }
+        if let Some(verdict) = &query.verdict {
+            conditions.push("verdict_kind = ?");
+            params.push(Box::new(verdict.clone()));
+        }
+        if let Some(source) = query.source {
+            conditions.push("source = ?");
+            params.push(Box::new(source.as_str()));
+        }
+        if let Some(since) = query.since {
+            conditions.push("logged_at >= ?");
+            params.push(Box::new(since));
+        }
+        if let Some(until) = query.until {
+            conditions.push("logged_at < ?");
+            params.push(Box::new(until));
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT id, logged_at, raw, site_id, verdict, source, user_agent
+             FROM scan_logs {} ORDER BY logged_at DESC, id DESC LIMIT {}",
+            where_clause,
+            query_limit(query)
+        );
+
+        Ok(self.pool.read(|conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(
+                rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+                scan_log_from_row,
+            )?;
+            rows.collect()
+        })?)
+    }
+
+    fn prune(&self, before: CheckTime) -> Result<usize, RepositoryError> {
+        Ok(self.pool.write(|conn| {
+            conn.execute(
+                "DELETE FROM scan_logs WHERE logged_at < ?1",
+                rusqlite::params![before],
+            )
+        })?)
+    }
+}
+
+// 不落盘的实现，与 InMemoryClassRepository 一起使用
+#[derive(Default)]
+pub struct InMemoryScanLogRepository {
+    logs: Mutex<Vec<ScanLogEntry>>,
+}
+
+impl InMemoryScanLogRepository {
+    fn with_logs<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<ScanLogEntry>) -> T,
+    ) -> Result<T, RepositoryError> {
+        let mut logs = self
+            .logs
+            .lock()
+            .map_err(|e| RepositoryError::Storage(format!("lock poisoned: {}", e)))?;
+        Ok(f(&mut logs))
+    }
+}
+
+impl ScanLogRepository for InMemoryScanLogRepository {
+    fn append(&self, log: NewScanLog) -> Result<(), RepositoryError> {
+        self.with_logs(|logs| {
+            let id = logs.last().map_or(1, |entry| entry.id + 1);
+            logs.push(ScanLogEntry {
+                id,
+                logged_at: CheckTime::now(),
+                raw: log.raw,
+                site_id: log.site_id,
+                verdict: log.verdict,
+                source: log.source,
+                user_agent: log.user_agent,
+            });
+        })
+    }
+
+    fn query(&self, query: &ScanLogQuery) -> Result<Vec<ScanLogEntry>, RepositoryError> {
+        self.with_logs(|logs| {
+            logs.iter()
+                .rev()
+                .filter(|entry| {
+                    query
+                        .site_id
+                        .as_ref()
+                        .is_none_or(|site_id| entry.site_id.as_ref() == Some(site_id))
+                        && query
+                            .verdict
+                            .as_ref()
+                            .is_none_or(|kind| entry.verdict.kind() == kind)
+                        && query.source.is_none_or(|source| entry.source == source)
+                        && query.since.is_none_or(|since| entry.logged_at >= since)
+                        && query.until.is_none_or(|until| entry.logged_at < until)
+                })
+                .take(query_limit(query) as usize)
+                .cloned()
+                .collect()
+        })
+    }
+
+    fn prune(&self, before: CheckTime) -> Result<usize, RepositoryError> {
+        self.with_logs(|logs| {
+            let count = logs.len();
+            logs.retain(|entry| entry.logged_at >= before);
+            count - logs.len()
+        })
+    }
+}
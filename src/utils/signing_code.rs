@@ -1,30 +1,598 @@
-use crate::models::SigningCode;
-use regex::Regex;
-use crate::models::ClassData;
-use crate::utils::time::get_formatted_time;
+use crate::models::{ClassData, SaveResult, SaveStatus, SigningCode};
+use crate::utils::time::{time_diff_from_now, CheckTime, Clock};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const SIGNING_CODE_PREFIX: &str = "checkwork|";
+
+// 签到码的有效期（分钟）
+pub const SIGNING_CODE_TTL_MINUTES: i64 = 200;
+
+// 签到码中的字段，按出现顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SigningCodeField {
+    Id,
+    SiteId,
+    CreateTime,
+    ClassLessonId,
+}
+
+impl SigningCodeField {
+    const ALL: [SigningCodeField; 4] = [
+        SigningCodeField::Id,
+        SigningCodeField::SiteId,
+        SigningCodeField::CreateTime,
+        SigningCodeField::ClassLessonId,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            SigningCodeField::Id => "id",
+            SigningCodeField::SiteId => "siteId",
+            SigningCodeField::CreateTime => "createTime",
+            SigningCodeField::ClassLessonId => "classLessonId",
+        }
+    }
+}
+
+impl fmt::Display for SigningCodeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+// 签到码解析失败的原因，position 为出错处在原始内容中的字节偏移
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SigningCodeError {
+    Empty,
+    MissingPrefix,
+    MissingField {
+        field: SigningCodeField,
+        position: usize,
+    },
+    UnexpectedField {
+        expected: SigningCodeField,
+        found: String,
+        position: usize,
+    },
+    EmptyValue {
+        field: SigningCodeField,
+        position: usize,
+    },
+    NonNumeric {
+        field: SigningCodeField,
+        value: String,
+        position: usize,
+    },
+    MalformedCreateTime {
+        value: String,
+        position: usize,
+    },
+    TrailingData {
+        data: String,
+        position: usize,
+    },
+}
+
+impl fmt::Display for SigningCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningCodeError::Empty => write!(f, "内容为空"),
+            SigningCodeError::MissingPrefix => {
+                write!(f, "不是签到码（缺少 {} 前缀）", SIGNING_CODE_PREFIX)
+            }
+            SigningCodeError::MissingField { field, position } => {
+                write!(f, "缺少字段 {}（位置 {}）", field, position)
+            }
+            SigningCodeError::UnexpectedField {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "字段顺序错误：期望 {}，实际为 {}（位置 {}）",
+                expected, found, position
+            ),
+            SigningCodeError::EmptyValue { field, position } => {
+                write!(f, "字段 {} 的值为空（位置 {}）", field, position)
+            }
+            SigningCodeError::NonNumeric {
+                field,
+                value,
+                position,
+            } => write!(
+                f,
+                "字段 {} 应为数字，实际为 \"{}\"（位置 {}）",
+                field, value, position
+            ),
+            SigningCodeError::MalformedCreateTime { value, position } => {
+                write!(f, "createTime 格式错误：\"{}\"（位置 {}）", value, position)
+            }
+            SigningCodeError::TrailingData { data, position } => {
+                write!(f, "签到码末尾有多余内容 \"{}\"（位置 {}）", data, position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SigningCodeError {}
 
 // 解析签到码内容
-pub fn parse_signing_code(content: &str) -> Option<SigningCode> {
-    let regex = Regex::new(r"checkwork\|id=(\d+)&siteId=(\d+)&createTime=([^&]+)&classLessonId=(\d+)").ok()?;
-    if let Some(captures) = regex.captures(content) {
-        Some(SigningCode {
-            id: captures.get(1)?.as_str().to_string(),
-            site_id: captures.get(2)?.as_str().to_string(),
-            create_time: captures.get(3)?.as_str().to_string(),
-            class_lesson_id: captures.get(4)?.as_str().to_string(),
-        })
+pub fn parse_signing_code(content: &str) -> Result<SigningCode, SigningCodeError> {
+    let start = content.len() - content.trim_start().len();
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return Err(SigningCodeError::Empty);
+    }
+    if !trimmed.starts_with(SIGNING_CODE_PREFIX) {
+        return Err(SigningCodeError::MissingPrefix);
+    }
+
+    let end = start + trimmed.len();
+    let mut position = start + SIGNING_CODE_PREFIX.len();
+    // 每个字段的值及其在原始内容中的位置
+    let mut values = Vec::with_capacity(SigningCodeField::ALL.len());
+
+    for (index, field) in SigningCodeField::ALL.into_iter().enumerate() {
+        if index > 0 {
+            // 除第一个字段外，每个字段前都应有 '&' 分隔符
+            if position >= end {
+                return Err(SigningCodeError::MissingField { field, position });
+            }
+            position += 1;
+        }
+
+        let rest = &content[position..end];
+        let segment = rest.split('&').next().unwrap_or_default();
+        let Some((key, value)) = segment.split_once('=') else {
+            if segment.is_empty() {
+                return Err(SigningCodeError::MissingField { field, position });
+            }
+            return Err(SigningCodeError::UnexpectedField {
+                expected: field,
+                found: segment.to_string(),
+                position,
+            });
+        };
+        if key != field.key() {
+            return Err(SigningCodeError::UnexpectedField {
+                expected: field,
+                found: key.to_string(),
+                position,
+            });
+        }
+
+        let value_position = position + key.len() + 1;
+        if value.is_empty() {
+            return Err(SigningCodeError::EmptyValue {
+                field,
+                position: value_position,
+            });
+        }
+        values.push((value, value_position));
+        position += segment.len();
+    }
+
+    if position < end {
+        return Err(SigningCodeError::TrailingData {
+            data: content[position..end].to_string(),
+            position,
+        });
+    }
+
+    let (create_time, create_time_position) = values[2];
+    let create_time =
+        create_time
+            .parse::<CheckTime>()
+            .map_err(|_| SigningCodeError::MalformedCreateTime {
+                value: create_time.to_string(),
+                position: create_time_position,
+            })?;
+    Ok(SigningCode {
+        id: numeric_value(SigningCodeField::Id, values[0])?,
+        site_id: numeric_value(SigningCodeField::SiteId, values[1])?,
+        create_time,
+        class_lesson_id: numeric_value(SigningCodeField::ClassLessonId, values[3])?,
+    })
+}
+
+fn numeric_value(
+    field: SigningCodeField,
+    (value, position): (&str, usize),
+) -> Result<String, SigningCodeError> {
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(value.to_string())
     } else {
-        None
+        Err(SigningCodeError::NonNumeric {
+            field,
+            value: value.to_string(),
+            position,
+        })
     }
 }
 
-pub fn format_signing_code(code: &ClassData) -> String {
+// 签到码距今的分钟数
+pub fn signing_code_age(code: &SigningCode, clock: &dyn Clock) -> i64 {
+    time_diff_from_now(clock, &code.create_time)
+}
+
+// 签到码是否已超过有效期
+pub fn is_signing_code_expired(code: &SigningCode, clock: &dyn Clock) -> bool {
+    signing_code_age(code, clock) > SIGNING_CODE_TTL_MINUTES
+}
+
+// 签到码是否比数据库中保存的签到码更旧。直接比较生成时间，与当前时间无关，
+// 生成时间相同（重复扫同一个码）不算更旧
+pub fn is_older_than_stored(code: &SigningCode, stored_time: &CheckTime) -> bool {
+    code.create_time < *stored_time
+}
+
+// 一次扫码的结论，客户端校验、服务器保存都返回这一类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanVerdict {
+    // 新课程，已保存；share_slug 见 ClassData，旧的扫码日志中没有该字段
+    AcceptedNew {
+        id: i64,
+        saved_at: CheckTime,
+        #[serde(default)]
+        share_slug: Option<String>,
+    },
+    // 已有课程，已更新签到信息；new_lesson 表示这是该课程新的一节课
+    AcceptedUpdated {
+        id: i64,
+        saved_at: CheckTime,
+        new_lesson: bool,
+        #[serde(default)]
+        share_slug: Option<String>,
+    },
+    // 超过有效期
+    Expired { age_minutes: i64 },
+    // 早于数据库中已保存的签到码
+    Stale { scanned: CheckTime, stored: CheckTime },
+    BadFormat(SigningCodeError),
+    // 保存失败（数据库或网络错误）
+    StorageFailure(String),
+}
+
+impl ScanVerdict {
+    pub fn is_accepted(&self) -> bool {
+        self.class_id().is_some()
+    }
+
+    // 保存成功时对应课程的 id
+    pub fn class_id(&self) -> Option<i64> {
+        match self {
+            ScanVerdict::AcceptedNew { id, .. } | ScanVerdict::AcceptedUpdated { id, .. } => {
+                Some(*id)
+            }
+            _ => None,
+        }
+    }
+
+    // 保存成功后跳转的签到码页面，有分享链接时优先使用分享链接
+    pub fn class_path(&self) -> Option<String> {
+        match self {
+            ScanVerdict::AcceptedNew {
+                share_slug: Some(slug),
+                ..
+            }
+            | ScanVerdict::AcceptedUpdated {
+                share_slug: Some(slug),
+                ..
+            } => Some(format!("/s/{}", slug)),
+            _ => self.class_id().map(|id| format!("/i/{}", id)),
+        }
+    }
+
+    // 结论的类型名，用于记录和筛选扫码日志
+    #[cfg(feature = "server")]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScanVerdict::AcceptedNew { .. } => "accepted_new",
+            ScanVerdict::AcceptedUpdated { .. } => "accepted_updated",
+            ScanVerdict::Expired { .. } => "expired",
+            ScanVerdict::Stale { .. } => "stale",
+            ScanVerdict::BadFormat(_) => "bad_format",
+            ScanVerdict::StorageFailure(_) => "storage_failure",
+        }
+    }
+
+    // 结论的简短标题
+    pub fn title(&self) -> &'static str {
+        match self {
+            ScanVerdict::AcceptedNew { .. } => "新课程签到信息已保存",
+            ScanVerdict::AcceptedUpdated { .. } => "课程签到信息已更新",
+            ScanVerdict::Expired { .. } => "二维码已过期",
+            ScanVerdict::Stale { .. } => "二维码不是最新的",
+            ScanVerdict::BadFormat(_) => "不是有效的签到码",
+            ScanVerdict::StorageFailure(_) => "保存失败",
+        }
+    }
+}
+
+impl From<SaveResult> for ScanVerdict {
+    fn from(result: SaveResult) -> Self {
+        match result.status {
+            SaveStatus::Created => ScanVerdict::AcceptedNew {
+                id: result.id,
+                saved_at: result.saved_at,
+                share_slug: result.share_slug,
+            },
+            SaveStatus::Updated => ScanVerdict::AcceptedUpdated {
+                id: result.id,
+                saved_at: result.saved_at,
+                new_lesson: result.new_lesson,
+                share_slug: result.share_slug,
+            },
+        }
+    }
+}
+
+// 检查签到码是否可以保存：未过期，且不早于数据库中已保存的签到码
+pub fn validate_signing_code(
+    code: &SigningCode,
+    stored_time: Option<&CheckTime>,
+    clock: &dyn Clock,
+) -> Result<(), ScanVerdict> {
+    if is_signing_code_expired(code, clock) {
+        return Err(ScanVerdict::Expired {
+            age_minutes: signing_code_age(code, clock),
+        });
+    }
+    if let Some(stored_time) = stored_time {
+        if is_older_than_stored(code, stored_time) {
+            return Err(ScanVerdict::Stale {
+                scanned: code.create_time,
+                stored: *stored_time,
+            });
+        }
+    }
+    Ok(())
+}
+
+// 解析并校验扫到的原始内容，客户端和服务器共用。
+// 客户端不知道已保存的签到码，stored_time 传 None；服务器在写入事务中传入已保存的时间
+pub fn validate_scan(
+    raw: &str,
+    stored_time: Option<&CheckTime>,
+    clock: &dyn Clock,
+) -> Result<SigningCode, ScanVerdict> {
+    let code = parse_signing_code(raw).map_err(ScanVerdict::BadFormat)?;
+    validate_signing_code(&code, stored_time, clock)?;
+    Ok(code)
+}
+
+pub fn format_signing_code(code: &ClassData, clock: &dyn Clock) -> String {
     format!(
         "checkwork|id={}&siteId={}&createTime={}&classLessonId={}",
         code.last_checkwork_id.as_ref().unwrap_or(&"".to_string()),
         code.site_id,
         // code.last_created_time.as_ref().unwrap_or(&"".to_string()),
-        get_formatted_time(),
-        code.last_class_lesson_id.as_ref().unwrap_or(&"".to_string())
+        clock.now(),
+        code.last_class_lesson_id
+            .as_ref()
+            .unwrap_or(&"".to_string())
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::FixedClock;
+    use chrono::Duration;
+
+    fn time(s: &str) -> CheckTime {
+        s.parse().unwrap()
+    }
+
+    fn code_created_at(create_time: CheckTime) -> SigningCode {
+        SigningCode {
+            id: "1".to_string(),
+            site_id: "2".to_string(),
+            create_time,
+            class_lesson_id: "3".to_string(),
+        }
+    }
+
+    // 当前时间固定为 now，签到码生成于 minutes 分钟前
+    fn code_aged(now: CheckTime, minutes: i64) -> SigningCode {
+        code_created_at(now - Duration::minutes(minutes))
+    }
+
+    #[test]
+    fn code_is_valid_until_ttl_is_exceeded() {
+        let now = time("2025-03-01T12:00:00.000");
+        let clock = FixedClock(now);
+
+        assert!(validate_signing_code(&code_aged(now, 199), None, &clock).is_ok());
+        assert!(
+            validate_signing_code(&code_aged(now, SIGNING_CODE_TTL_MINUTES), None, &clock).is_ok()
+        );
+        assert_eq!(
+            validate_signing_code(&code_aged(now, 201), None, &clock),
+            Err(ScanVerdict::Expired { age_minutes: 201 })
+        );
+    }
+
+    #[test]
+    fn code_from_the_future_is_judged_by_distance_from_now() {
+        let now = time("2025-03-01T12:00:00.000");
+        let clock = FixedClock(now);
+
+        // 客户端时钟略快时生成时间会在将来，在有效期内仍可使用
+        assert!(validate_signing_code(&code_aged(now, -5), None, &clock).is_ok());
+        assert_eq!(
+            validate_signing_code(&code_aged(now, -201), None, &clock),
+            Err(ScanVerdict::Expired { age_minutes: 201 })
+        );
+    }
+
+    #[test]
+    fn code_older_than_stored_is_stale() {
+        let clock = FixedClock(time("2025-03-01T12:00:00.000"));
+        let stored = time("2025-03-01T11:00:00.000");
+
+        let older = code_created_at(time("2025-03-01T10:59:59.999"));
+        assert!(is_older_than_stored(&older, &stored));
+        assert_eq!(
+            validate_signing_code(&older, Some(&stored), &clock),
+            Err(ScanVerdict::Stale {
+                scanned: older.create_time,
+                stored,
+            })
+        );
+
+        let equal = code_created_at(stored);
+        assert!(!is_older_than_stored(&equal, &stored));
+        assert!(validate_signing_code(&equal, Some(&stored), &clock).is_ok());
+
+        let newer = code_created_at(time("2025-03-01T11:30:00.000"));
+        assert!(!is_older_than_stored(&newer, &stored));
+        assert!(validate_signing_code(&newer, Some(&stored), &clock).is_ok());
+    }
+
+    #[test]
+    fn expiry_is_checked_before_freshness() {
+        let now = time("2025-03-01T12:00:00.000");
+        let clock = FixedClock(now);
+        let stored = now - Duration::minutes(10);
+
+        assert_eq!(
+            validate_signing_code(&code_aged(now, 300), Some(&stored), &clock),
+            Err(ScanVerdict::Expired { age_minutes: 300 })
+        );
+    }
+
+    #[test]
+    fn validate_scan_uses_injected_clock() {
+        let raw = "checkwork|id=1&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3";
+
+        let clock = FixedClock(time("2025-03-01T12:00:00.000"));
+        let code = validate_scan(raw, None, &clock).unwrap();
+        assert_eq!(code.create_time, time("2025-03-01T10:00:00.000"));
+
+        let clock = FixedClock(time("2025-03-01T13:21:00.000"));
+        assert_eq!(
+            validate_scan(raw, None, &clock).unwrap_err(),
+            ScanVerdict::Expired { age_minutes: 201 }
+        );
+    }
+
+    const VALID_CODE: &str =
+        "checkwork|id=1&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3";
+
+    #[test]
+    fn parses_a_valid_code() {
+        let code = parse_signing_code(&format!("  {}\n", VALID_CODE)).unwrap();
+        assert_eq!(code.id, "1");
+        assert_eq!(code.site_id, "2");
+        assert_eq!(code.create_time, time("2025-03-01T10:00:00.000"));
+        assert_eq!(code.class_lesson_id, "3");
+    }
+
+    #[test]
+    fn reports_the_error_and_position_for_malformed_codes() {
+        use SigningCodeError::*;
+        use SigningCodeField::*;
+
+        let cases: Vec<(&str, SigningCodeError)> = vec![
+            ("", Empty),
+            ("   \n", Empty),
+            ("https://example.com/?id=1", MissingPrefix),
+            ("CHECKWORK|id=1", MissingPrefix),
+            (
+                "checkwork|",
+                MissingField {
+                    field: Id,
+                    position: 10,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2",
+                MissingField {
+                    field: CreateTime,
+                    position: 23,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&",
+                MissingField {
+                    field: CreateTime,
+                    position: 24,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&createTi",
+                UnexpectedField {
+                    expected: CreateTime,
+                    found: "createTi".to_string(),
+                    position: 24,
+                },
+            ),
+            (
+                "checkwork|siteId=2&id=1&createTime=2025-03-01T10:00:00.000&classLessonId=3",
+                UnexpectedField {
+                    expected: Id,
+                    found: "siteId".to_string(),
+                    position: 10,
+                },
+            ),
+            (
+                "checkwork|id=&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3",
+                EmptyValue {
+                    field: Id,
+                    position: 13,
+                },
+            ),
+            (
+                "checkwork|id=1a&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3",
+                NonNumeric {
+                    field: Id,
+                    value: "1a".to_string(),
+                    position: 13,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=x3",
+                NonNumeric {
+                    field: ClassLessonId,
+                    value: "x3".to_string(),
+                    position: 73,
+                },
+            ),
+            // 位置按原始内容计算，包括开头的空白
+            (
+                "  checkwork|id=x&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3",
+                NonNumeric {
+                    field: Id,
+                    value: "x".to_string(),
+                    position: 15,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&createTime=2025-13-01T10:00:00.000&classLessonId=3",
+                MalformedCreateTime {
+                    value: "2025-13-01T10:00:00.000".to_string(),
+                    position: 35,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&createTime=yesterday&classLessonId=3",
+                MalformedCreateTime {
+                    value: "yesterday".to_string(),
+                    position: 35,
+                },
+            ),
+            (
+                "checkwork|id=1&siteId=2&createTime=2025-03-01T10:00:00.000&classLessonId=3&extra=1",
+                TrailingData {
+                    data: "&extra=1".to_string(),
+                    position: 74,
+                },
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_signing_code(input).unwrap_err(), expected, "{:?}", input);
+        }
+    }
+}
@@ -1,107 +1,377 @@
-#[cfg(not(feature = "server"))]
-pub fn format_current_time() -> String {
-    use js_sys::Date;
-    let now = Date::new_0();
-    
-    // 获取本地时间（不手动调整时区）
-    let year = now.get_full_year() as u32;
-    let month = (now.get_month() as u32) + 1;
-    let day = now.get_date() as u32;
-    let hours = now.get_hours() as u32;
-    let minutes = now.get_minutes() as u32;
-    let seconds = now.get_seconds() as u32;
-    let milliseconds = now.get_milliseconds() as u32;
-    
-    format!(
-        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
-        year, month, day, hours, minutes, seconds, milliseconds
-    )
-}
-
-pub fn get_formatted_time() -> String {
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+// 签到码及数据库中统一使用的时间格式（WIRE_OFFSET_SECS 时区的墙上时间，不带时区后缀）
+pub const CHECK_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f";
+
+// 签到码中的 createTime 总是北京时间。读写签到码、数据库和接口时固定使用这一偏移，
+// 与 APP_TIMEZONE 无关；APP_TIMEZONE 只影响显示
+pub const WIRE_OFFSET_SECS: i32 = 8 * 3600;
+
+// 页面上显示时间的格式
+const DISPLAY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn wire_offset() -> FixedOffset {
+    FixedOffset::east_opt(WIRE_OFFSET_SECS).unwrap()
+}
+
+// 服务端读取时区配置的环境变量，例如 "Asia/Shanghai" 或 "+08:00"
+#[cfg(feature = "server")]
+const TIME_ZONE_ENV: &str = "APP_TIMEZONE";
+
+// 页面显示时间使用的时区：固定偏移或 IANA 时区（支持夏令时）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppTimeZone {
+    Fixed(FixedOffset),
+    Named(chrono_tz::Tz),
+}
+
+impl Default for AppTimeZone {
+    // 默认为北京时间
+    fn default() -> Self {
+        AppTimeZone::Fixed(wire_offset())
+    }
+}
+
+impl AppTimeZone {
+    // UTC 时间对应的本地墙上时间
+    pub fn local_from_utc(&self, utc: &DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            AppTimeZone::Fixed(offset) => utc.with_timezone(offset).naive_local(),
+            AppTimeZone::Named(tz) => utc.with_timezone(tz).naive_local(),
+        }
+    }
+}
+
+impl fmt::Display for AppTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppTimeZone::Fixed(offset) => write!(f, "{}", offset),
+            AppTimeZone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+impl FromStr for AppTimeZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(tz) = s.parse::<chrono_tz::Tz>() {
+            return Ok(AppTimeZone::Named(tz));
+        }
+        s.parse::<FixedOffset>()
+            .map(AppTimeZone::Fixed)
+            .map_err(|_| format!("无法识别的时区: {}", s))
+    }
+}
+
+impl Serialize for AppTimeZone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppTimeZone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+static APP_TIME_ZONE: RwLock<Option<AppTimeZone>> = RwLock::new(None);
+
+// 当前使用的时区。服务端首次调用时读取配置，客户端由服务器下发
+pub fn app_time_zone() -> AppTimeZone {
+    if let Some(tz) = *APP_TIME_ZONE.read().unwrap() {
+        return tz;
+    }
+    let tz = configured_time_zone();
+    set_app_time_zone(tz);
+    tz
+}
+
+pub fn set_app_time_zone(tz: AppTimeZone) {
+    *APP_TIME_ZONE.write().unwrap() = Some(tz);
+}
+
+fn configured_time_zone() -> AppTimeZone {
     #[cfg(feature = "server")]
-    {
-        use chrono::{FixedOffset, Utc};
-        let china_tz = FixedOffset::east_opt(8 * 3600).unwrap();
-        Utc::now()
-            .with_timezone(&china_tz)
-            .format("%Y-%m-%dT%H:%M:%S%.3f")
+    if let Ok(value) = std::env::var(TIME_ZONE_ENV) {
+        match value.parse() {
+            Ok(tz) => return tz,
+            Err(e) => println!("Warning: {}, falling back to default time zone", e),
+        }
+    }
+    AppTimeZone::default()
+}
+
+// 签到时间，内部保存 UTC 时刻，比较和相减的结果总是一致。
+// Display/FromStr 是签到码、数据库和接口使用的格式，页面显示用 to_local_string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckTime(DateTime<Utc>);
+
+impl CheckTime {
+    // 当前时间。服务端和 wasm 端都从 UTC 毫秒数换算，保证结果一致
+    pub fn now() -> Self {
+        #[cfg(feature = "server")]
+        let millis = Utc::now().timestamp_millis();
+        #[cfg(not(feature = "server"))]
+        let millis = js_sys::Date::now() as i64;
+        Self::from_timestamp_millis(millis).expect("current time out of range")
+    }
+
+    // 由 Unix 毫秒时间戳构造
+    pub fn from_timestamp_millis(millis: i64) -> Option<Self> {
+        let utc = DateTime::<Utc>::from_timestamp_millis(millis)?;
+        Some(Self::from_utc(&utc))
+    }
+
+    pub fn from_utc(utc: &DateTime<Utc>) -> Self {
+        Self(*utc)
+    }
+
+    pub fn to_utc(self) -> DateTime<Utc> {
+        self.0
+    }
+
+    // 对应的 Unix 毫秒时间戳
+    pub fn timestamp_millis(&self) -> i64 {
+        self.0.timestamp_millis()
+    }
+
+    // 在指定时区显示
+    pub fn format_in(&self, tz: &AppTimeZone) -> String {
+        tz.local_from_utc(&self.0)
+            .format(DISPLAY_TIME_FORMAT)
             .to_string()
     }
-    #[cfg(not(feature = "server"))]
-    {
-        format_current_time()
+
+    // 在应用时区（APP_TIMEZONE，客户端由服务器下发）显示
+    pub fn to_local_string(self) -> String {
+        self.format_in(&app_time_zone())
+    }
+
+    // 从 self 到 later 经过的分钟数，later 更早时为负数
+    pub fn minutes_until(&self, later: &CheckTime) -> i64 {
+        (*later - *self).num_minutes()
     }
 }
 
-pub fn time_diff_in_minutes(time1: &str, time2: &str) -> Option<i64> {
-    #[cfg(feature = "server")]
-    {
-        use chrono::DateTime;
-        let datetime1 = DateTime::parse_from_rfc3339(time1).ok()?;
-        let datetime2 = DateTime::parse_from_rfc3339(time2).ok()?;
-        let duration = datetime2.signed_duration_since(datetime1);
-        Some(duration.num_minutes().abs())
-    }
-    #[cfg(not(feature = "server"))]
-    {
-        use js_sys::Date;
-        let timestamp1 = Date::parse(time1);
-        let timestamp2 = Date::parse(time2);
-        if timestamp1.is_nan() || timestamp2.is_nan() {
-            return None;
+impl fmt::Display for CheckTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .with_timezone(&wire_offset())
+                .format(CHECK_TIME_FORMAT)
+        )
+    }
+}
+
+impl FromStr for CheckTime {
+    type Err = chrono::ParseError;
+
+    // 不带时区的时间（签到码格式或 "YYYY-MM-DD HH:MM:SS"）按 WIRE_OFFSET_SECS 解释，
+    // 也接受带时区的 RFC3339
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"));
+        match naive {
+            Ok(datetime) => Ok(Self(
+                (datetime - Duration::seconds(WIRE_OFFSET_SECS as i64)).and_utc(),
+            )),
+            Err(_) => DateTime::parse_from_rfc3339(s)
+                .map(|datetime| Self::from_utc(&datetime.with_timezone(&Utc))),
         }
-        let diff_ms = (timestamp2 - timestamp1).abs();
-        let diff_minutes = (diff_ms / (1000.0 * 60.0)).floor() as i64;
-        Some(diff_minutes)
     }
 }
 
+impl Add<Duration> for CheckTime {
+    type Output = CheckTime;
+
+    fn add(self, rhs: Duration) -> CheckTime {
+        Self(self.0 + rhs)
+    }
+}
+
+impl Sub<Duration> for CheckTime {
+    type Output = CheckTime;
+
+    fn sub(self, rhs: Duration) -> CheckTime {
+        Self(self.0 - rhs)
+    }
+}
+
+impl Sub for CheckTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: CheckTime) -> Duration {
+        self.0 - rhs.0
+    }
+}
+
+impl Serialize for CheckTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CheckTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "server")]
+impl rusqlite::types::ToSql for CheckTime {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(rusqlite::types::ToSqlOutput::from(self.to_string()))
+    }
+}
+
+#[cfg(feature = "server")]
+impl rusqlite::types::FromSql for CheckTime {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e| rusqlite::types::FromSqlError::Other(Box::new(e)))
+    }
+}
+
+// 时钟抽象，所有依赖“当前时间”的逻辑都通过它取时间
+pub trait Clock {
+    fn now(&self) -> CheckTime;
+}
+
+// 读取系统时间的时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> CheckTime {
+        CheckTime::now()
+    }
+}
+
+// 始终返回同一时刻的时钟
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub CheckTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> CheckTime {
+        self.0
+    }
+}
+
+// 在另一个时钟的基础上加上固定偏移的时钟
+#[derive(Debug, Clone, Copy)]
+pub struct OffsetClock<C: Clock> {
+    pub inner: C,
+    pub offset: Duration,
+}
+
+impl<C: Clock> Clock for OffsetClock<C> {
+    fn now(&self) -> CheckTime {
+        self.inner.now() + self.offset
+    }
+}
+
+pub fn time_diff_in_minutes(time1: &CheckTime, time2: &CheckTime) -> i64 {
+    time1.minutes_until(time2).abs()
+}
+
 // 计算从给定时间到当前时间的时间差（分钟）
-pub fn time_diff_from_now(from_time: &str) -> i64 {
-    let current_time = get_formatted_time();
-    time_diff_in_minutes(from_time, &current_time).unwrap_or(0)
+pub fn time_diff_from_now(clock: &dyn Clock, from_time: &CheckTime) -> i64 {
+    time_diff_in_minutes(from_time, &clock.now())
 }
 
-pub fn compare_formatted_time(time1: &str, time2: &str, gap_time: u64) -> bool {
-    #[cfg(feature = "server")]
-    {
-        use chrono::{DateTime, FixedOffset};
-        let china_tz = FixedOffset::east_opt(8 * 3600).unwrap();
-        let datetime1 = match DateTime::parse_from_str(&format!("{}+08:00", time1), "%Y-%m-%dT%H:%M:%S%.3f%z") {
-            Ok(dt) => dt.with_timezone(&china_tz),
-            Err(_) => return false,
-        };
-        let datetime2 = match DateTime::parse_from_str(&format!("{}+08:00", time2), "%Y-%m-%dT%H:%M:%S%.3f%z") {
-            Ok(dt) => dt.with_timezone(&china_tz),
-            Err(_) => return false,
-        };
-        let duration = datetime2.signed_duration_since(datetime1);
-        let diff_minutes = duration.num_minutes();
-        diff_minutes >= 0 && diff_minutes <= gap_time as i64
-    }
-    #[cfg(not(feature = "server"))]
-    {
-        use js_sys::Date;
-        let parse_time = |time_str: &str| -> Option<f64> {
-            let parts: Vec<&str> = time_str.split('T').collect();
-            if parts.len() != 2 { return None; }
-            let date_parts: Vec<&str> = parts[0].split('-').collect();
-            if date_parts.len() != 3 { return None; }
-            let time_parts: Vec<&str> = parts[1].split(':').collect();
-            if time_parts.len() != 3 { return None; }
-            let seconds_parts: Vec<&str> = time_parts[2].split('.').collect();
-            if seconds_parts.len() != 2 { return None; }
-            let iso_string = format!("{}T{}Z", parts[0], parts[1]);
-            let date = Date::new(&iso_string.into());
-            let utc_time = date.get_time();
-            let china_time = utc_time + 8.0 * 60.0 * 60.0 * 1000.0;
-            Some(china_time)
-        };
-        let timestamp1 = match parse_time(time1) { Some(t) => t, None => return false, };
-        let timestamp2 = match parse_time(time2) { Some(t) => t, None => return false, };
-        let diff_ms = timestamp2 - timestamp1;
-        let diff_minutes = (diff_ms / (1000.0 * 60.0)) as i64;
-        diff_minutes >= 0 && diff_minutes <= gap_time as i64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn wire_format_is_fixed_at_plus_eight() {
+        let time = CheckTime::from_utc(&utc("2024-03-10T00:30:00Z"));
+        assert_eq!(time.to_string(), "2024-03-10T08:30:00.000");
+        assert_eq!(
+            "2024-03-10T08:30:00.000".parse::<CheckTime>().unwrap(),
+            time
+        );
+        assert_eq!("2024-03-10 08:30:00".parse::<CheckTime>().unwrap(), time);
+        assert_eq!(
+            "2024-03-10T02:30:00+02:00".parse::<CheckTime>().unwrap(),
+            time
+        );
+    }
+
+    #[test]
+    fn display_zone_does_not_affect_wire_format() {
+        let time: CheckTime = "2024-07-01T12:00:00.000".parse().unwrap();
+        let new_york = AppTimeZone::Named(chrono_tz::America::New_York);
+        assert_eq!(time.format_in(&new_york), "2024-07-01 00:00:00");
+        assert_eq!(time.to_string(), "2024-07-01T12:00:00.000");
+    }
+
+    #[test]
+    fn formats_in_fixed_offset() {
+        let time: CheckTime = "2024-07-01T12:00:00.000".parse().unwrap();
+        let kolkata = AppTimeZone::Fixed("+05:30".parse().unwrap());
+        assert_eq!(time.format_in(&kolkata), "2024-07-01 09:30:00");
+        assert_eq!(
+            time.format_in(&AppTimeZone::default()),
+            "2024-07-01 12:00:00"
+        );
+    }
+
+    // 纽约 2024-11-03 夏令时结束，01:00-02:00 出现两次
+    #[test]
+    fn order_and_difference_agree_across_dst_fall_back() {
+        let new_york = AppTimeZone::Named(chrono_tz::America::New_York);
+        let earlier = CheckTime::from_utc(&utc("2024-11-03T05:50:00Z"));
+        let later = CheckTime::from_utc(&utc("2024-11-03T06:10:00Z"));
+        assert_eq!(earlier.format_in(&new_york), "2024-11-03 01:50:00");
+        assert_eq!(later.format_in(&new_york), "2024-11-03 01:10:00");
+
+        assert!(earlier < later);
+        assert_eq!(later - earlier, Duration::minutes(20));
+        assert_eq!(earlier + Duration::minutes(20), later);
+        assert_eq!(time_diff_in_minutes(&earlier, &later), 20);
+    }
+
+    #[test]
+    fn repeated_wall_time_maps_to_distinct_instants() {
+        let new_york = AppTimeZone::Named(chrono_tz::America::New_York);
+        let daylight = CheckTime::from_utc(&utc("2024-11-03T05:30:00Z"));
+        let standard = CheckTime::from_utc(&utc("2024-11-03T06:30:00Z"));
+        assert_eq!(daylight.format_in(&new_york), standard.format_in(&new_york));
+        assert!(daylight < standard);
+        assert_eq!(standard - daylight, Duration::hours(1));
+        assert_ne!(daylight.to_string(), standard.to_string());
+    }
+
+    #[test]
+    fn parses_time_zone_settings() {
+        assert_eq!(
+            "Asia/Shanghai".parse::<AppTimeZone>().unwrap(),
+            AppTimeZone::Named(chrono_tz::Asia::Shanghai)
+        );
+        assert_eq!(
+            "+08:00".parse::<AppTimeZone>().unwrap(),
+            AppTimeZone::default()
+        );
+        assert!("Mars/Olympus".parse::<AppTimeZone>().is_err());
     }
 }